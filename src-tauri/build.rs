@@ -1,3 +1,127 @@
+use std::{
+    env,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use bincode::{config, Encode};
+use flate2::{write::GzEncoder, Compression};
+use shakmaty::{fen::Fen, san::San, uci::Uci, Chess, EnPassantMode, Position};
+
+const TSV_FILES: [&str; 5] = [
+    "data/a.tsv",
+    "data/b.tsv",
+    "data/c.tsv",
+    "data/d.tsv",
+    "data/e.tsv",
+];
+
+/// Must stay in sync with `IndexedOpening` in src/opening.rs
+#[derive(Encode)]
+struct IndexedOpening {
+    eco: String,
+    name: String,
+    fen: String,
+    pgn: String,
+    popularity: u32,
+}
+
 fn main() {
+    build_openings_index();
+    compress_tsv_files();
     tauri_build::build()
-}
\ No newline at end of file
+}
+
+/// Replays every opening of the TSV files once and writes the resolved table of
+/// each volume, gzipped, to `$OUT_DIR/<volume>.bin.gz`, so the app doesn't have to
+/// do it on startup.
+fn build_openings_index() {
+    let out_dir = env::var("OUT_DIR").unwrap();
+    for path in TSV_FILES {
+        println!("cargo:rerun-if-changed={}", path);
+        let mut openings = Vec::new();
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .from_path(path)
+            .unwrap();
+        for record in rdr.records() {
+            let record = record.unwrap();
+            let (Some(eco), Some(name), Some(pgn)) = (record.get(0), record.get(1), record.get(2))
+            else {
+                println!("cargo:warning=Skipping malformed opening in {}", path);
+                continue;
+            };
+            match fen_from_pgn(pgn) {
+                Some(fen) => openings.push(IndexedOpening {
+                    eco: eco.to_string(),
+                    name: name.to_string(),
+                    fen,
+                    pgn: pgn.to_string(),
+                    popularity: record.get(3).and_then(|p| p.parse().ok()).unwrap_or(0),
+                }),
+                None => println!("cargo:warning=Illegal move in opening {}: {}", name, pgn),
+            }
+        }
+
+        let volume = Path::new(path).file_stem().unwrap().to_str().unwrap();
+        let out_path = Path::new(&out_dir).join(format!("{}.bin.gz", volume));
+        let mut encoder = GzEncoder::new(
+            BufWriter::new(File::create(out_path).unwrap()),
+            Compression::best(),
+        );
+        bincode::encode_into_std_write(&openings, &mut encoder, config::standard()).unwrap();
+        encoder.finish().unwrap().flush().unwrap();
+    }
+}
+
+/// Writes a gzipped copy of every TSV file to `$OUT_DIR`, so the raw tables
+/// embedded as a fallback for the index take less space in the binary.
+fn compress_tsv_files() {
+    let out_dir = env::var("OUT_DIR").unwrap();
+    for path in TSV_FILES {
+        let file_name = Path::new(path).file_name().unwrap().to_str().unwrap();
+        let out_path = Path::new(&out_dir).join(format!("{}.gz", file_name));
+        let mut encoder = GzEncoder::new(File::create(out_path).unwrap(), Compression::best());
+        encoder.write_all(&fs::read(path).unwrap()).unwrap();
+        encoder.finish().unwrap();
+    }
+}
+
+fn fen_from_pgn(pgn: &str) -> Option<String> {
+    let mut pos = Chess::default();
+    for token in pgn.split_whitespace() {
+        if token.ends_with('.') && token.trim_end_matches('.').parse::<u32>().is_ok() {
+            continue;
+        }
+        let mv = token.trim_end_matches(['!', '?']);
+        let san = mv.parse::<San>().ok();
+        let uci = long_algebraic(mv);
+        if san.is_none() && uci.is_none() {
+            println!(
+                "cargo:warning=Dropping token {:?} of opening line {:?}",
+                token, pgn
+            );
+            continue;
+        }
+        let m = san
+            .and_then(|san| san.to_move(&pos).ok())
+            .or_else(|| uci.and_then(|uci| uci.to_move(&pos).ok()))?;
+        pos.play_unchecked(&m);
+    }
+    Some(Fen::from_position(pos, EnPassantMode::Legal).to_string())
+}
+
+/// Must stay in sync with `long_algebraic` in src/opening.rs
+fn long_algebraic(token: &str) -> Option<Uci> {
+    let token = token.trim_end_matches(['+', '#']);
+    let squares = token
+        .strip_prefix(['N', 'B', 'R', 'Q', 'K'])
+        .unwrap_or(token);
+    let uci: String = squares
+        .chars()
+        .filter(|c| !matches!(c, '-' | 'x' | '='))
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    Uci::from_ascii(uci.as_bytes()).ok()
+}
@@ -12,7 +12,7 @@ use crate::{
         schema::*,
     },
     error::Error,
-    opening::get_opening_from_setup,
+    opening::{get_opening_from_setup, GameVariant},
     AppState,
 };
 use chrono::{NaiveDate, NaiveTime};
@@ -1139,7 +1139,7 @@ pub async fn get_players_game_info(
 
             setups.reverse();
             for setup in setups {
-                if let Ok(opening) = get_opening_from_setup(setup) {
+                if let Ok(opening) = get_opening_from_setup(setup, GameVariant::Standard) {
                     let openings = if is_white {
                         &white_openings
                     } else {
@@ -39,6 +39,9 @@ pub enum Error {
     #[error(transparent)]
     ParseUci(#[from] shakmaty::uci::ParseUciError),
 
+    #[error(transparent)]
+    ParseDate(#[from] chrono::ParseError),
+
     #[error(transparent)]
     Fen(#[from] shakmaty::fen::ParseFenError),
 
@@ -54,6 +57,12 @@ pub enum Error {
     #[error(transparent)]
     R2d2(#[from] diesel::r2d2::PoolError),
 
+    #[error(transparent)]
+    DieselConnection(#[from] diesel::ConnectionError),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
     #[error("No stdin")]
     NoStdin,
 
@@ -75,9 +84,57 @@ pub enum Error {
     #[error("No opening found")]
     NoOpeningFound,
 
+    #[error("No opening found, did you mean {suggestion}?")]
+    NoOpeningFoundDidYouMean { suggestion: String },
+
+    #[error("Position has no opening name")]
+    PositionNotNamed,
+
     #[error("No match found")]
     NoMatchFound,
 
+    #[error("Search cancelled by a newer search")]
+    SearchCancelled,
+
+    #[error("Child opening doesn't continue the parent by one move")]
+    NotAContinuation,
+
+    #[error("Illegal move {1} at ply {0}")]
+    IllegalMoveAtPly(usize, String),
+
+    #[error("Ply {0} is past the end of a game of {1} plies")]
+    PlyOutOfRange(usize, usize),
+
+    #[error("Openings were already loaded")]
+    OpeningsAlreadyLoaded,
+
+    #[error("Invalid FEN {input:?}: {reason}")]
+    InvalidFen { input: String, reason: String },
+
+    #[error("Each side must have exactly one king")]
+    InvalidKingCount,
+
+    #[error("Pawns can't be on the first or last rank")]
+    PawnsOnBackRank,
+
+    #[error("Too many pieces for a legal position")]
+    TooMuchMaterial,
+
+    #[error("Castling rights don't match the king and rook positions")]
+    InvalidCastlingRights,
+
+    #[error("Invalid en passant square")]
+    InvalidEpSquare,
+
+    #[error("The side not to move is in check")]
+    OppositeCheck,
+
+    #[error("Impossible check")]
+    ImpossibleCheck,
+
+    #[error("Invalid position: {0:?}")]
+    InvalidPosition(shakmaty::PositionErrorKinds),
+
     #[error("No puzzles")]
     NoPuzzles,
 }
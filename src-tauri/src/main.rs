@@ -12,6 +12,7 @@ mod lexer;
 mod oauth;
 mod opening;
 mod pgn;
+mod polyglot;
 mod puzzle;
 
 use std::path::PathBuf;
@@ -45,12 +46,33 @@ use crate::fs::{append_to_file, set_file_as_executable};
 use crate::lexer::lex_pgn;
 use crate::oauth::authenticate;
 use crate::pgn::{count_pgn_games, delete_game, read_games, write_game};
+use crate::polyglot::get_book_moves;
 use crate::puzzle::{get_puzzle, get_puzzle_db_info};
 use crate::{
     chess::get_best_moves,
     db::{edit_db_info, get_db_info, get_games, get_players},
     fs::download_file,
-    opening::{get_opening_from_fen, get_opening_from_name, search_opening_name},
+    opening::{
+        autocomplete_opening, classify_game, classify_positions, common_opening_ancestor,
+        eco_codes_for_name, eco_for_game, eco_volumes, export_opening_tree, export_openings_json,
+        export_openings_sqlite, find_novelty, get_all_openings_from_fen, get_deepest_opening,
+        get_fen_from_opening_name, get_full_opening_from_fen, get_opening_by_transposition,
+        get_opening_children, get_opening_from_board_placement, get_opening_from_fen,
+        get_opening_from_fen_relaxed, get_opening_from_hash, get_opening_from_mirrored_fen,
+        get_opening_from_moves_truncated, get_opening_from_name, get_opening_from_pgn,
+        get_opening_from_uci, get_opening_moves, get_opening_parent, get_openings_from_names,
+        get_recent_openings, is_book_move, is_in_opening_book, list_opening_names,
+        load_openings_from_dir, load_openings_ndjson, load_user_openings, nearest_named_opening,
+        next_search_generation, opening_counts_by_volume, opening_dataset_version, opening_depth,
+        opening_line_fens, opening_of_the_day, opening_siblings, opening_subtree, opening_synonyms,
+        opening_transition_move, openings_after_first_move, openings_with_pawn_structure,
+        preload_openings, random_opening, record_opening_view, reload_user_openings,
+        resolve_opening_alias, search_opening_by_eco, search_opening_by_eco_range,
+        search_opening_by_tag, search_opening_grouped, search_opening_in_family,
+        search_opening_name, search_opening_name_excluding, search_opening_name_scored,
+        search_opening_name_streamed, shortest_named_line_for_fen, split_opening_name,
+        suggest_next_opening_moves, truncate_opening_name, validate_fen, verify_opening_data,
+    },
 };
 use tokio::sync::{RwLock, Semaphore};
 
@@ -123,6 +145,7 @@ fn main() {
                 is_menu_visisble,
                 get_opening_from_fen,
                 get_opening_from_name,
+                get_fen_from_opening_name,
                 get_players_game_info,
             ))
             .events(tauri_specta::collect_events!(BestMovesPayload, Progress));
@@ -231,6 +254,72 @@ fn main() {
             lex_pgn,
             download_fide_db,
             search_opening_name,
+            get_full_opening_from_fen,
+            get_opening_by_transposition,
+            get_deepest_opening,
+            search_opening_by_eco,
+            search_opening_by_eco_range,
+            get_opening_moves,
+            get_opening_children,
+            get_opening_parent,
+            get_all_openings_from_fen,
+            load_user_openings,
+            reload_user_openings,
+            get_opening_from_mirrored_fen,
+            validate_fen,
+            find_novelty,
+            search_opening_grouped,
+            autocomplete_opening,
+            opening_counts_by_volume,
+            get_book_moves,
+            export_openings_sqlite,
+            classify_game,
+            get_opening_from_pgn,
+            classify_positions,
+            is_in_opening_book,
+            list_opening_names,
+            split_opening_name,
+            opening_transition_move,
+            export_opening_tree,
+            suggest_next_opening_moves,
+            get_opening_from_hash,
+            verify_opening_data,
+            search_opening_by_tag,
+            opening_depth,
+            search_opening_name_scored,
+            search_opening_name_excluding,
+            get_opening_from_moves_truncated,
+            resolve_opening_alias,
+            search_opening_name_streamed,
+            load_openings_from_dir,
+            openings_after_first_move,
+            opening_siblings,
+            nearest_named_opening,
+            opening_dataset_version,
+            search_opening_in_family,
+            get_opening_from_fen_relaxed,
+            export_openings_json,
+            get_opening_from_uci,
+            is_book_move,
+            get_openings_from_names,
+            eco_volumes,
+            shortest_named_line_for_fen,
+            load_openings_ndjson,
+            eco_codes_for_name,
+            openings_with_pawn_structure,
+            random_opening,
+            opening_line_fens,
+            opening_synonyms,
+            eco_for_game,
+            truncate_opening_name,
+            opening_of_the_day,
+            opening_subtree,
+            record_opening_view,
+            get_recent_openings,
+            common_opening_ancestor,
+            get_opening_from_board_placement,
+            preload_openings,
+            next_search_generation,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,152 +1,5128 @@
-use log::info;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque},
+    env,
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    num::NonZeroU32,
+    ops::Bound,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{self, AtomicU64, AtomicUsize},
+        Mutex, RwLock,
+    },
+};
+
+use bincode::{config, Decode};
+use chrono::NaiveDate;
+use diesel::{
+    connection::SimpleConnection,
+    sql_query,
+    sql_types::{Nullable, Text},
+    Connection, RunQueryDsl, SqliteConnection,
+};
+use flate2::read::GzDecoder;
+use log::{info, warn};
+use once_cell::sync::OnceCell;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 use serde::{Deserialize, Serialize, ser::SerializeStruct};
-use shakmaty::{fen::Fen, san::San, Chess, EnPassantMode, Position, Setup};
+use sha2::{Digest, Sha256};
+use shakmaty::{
+    fen::{Fen, ParseFenError},
+    san::{San, SanPlus},
+    uci::Uci,
+    Bitboard, Board, CastlingMode, Chess, Color, EnPassantMode, FromSetup, Move, Piece, Position,
+    PositionError, PositionErrorKinds, Role, Setup, Square,
+};
 
 use lazy_static::lazy_static;
-use strsim::jaro_winkler;
+use specta::Type;
+use strsim::{jaro_winkler, normalized_levenshtein, sorensen_dice};
+use tauri::Manager;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
 
 use crate::error::Error;
+use crate::polyglot::polyglot_key;
 
 #[derive(Debug, Clone)]
 pub struct Opening {
     eco: String,
     name: String,
     setup: Setup,
+    /// Canonical FEN of `setup`, rendered once since it's sent with every search result
+    fen: String,
     pgn: Option<String>,
+    /// How often the opening is played, used to rank otherwise similar search results
+    popularity: u32,
+    /// Themes of the opening, inferred from its name
+    tags: Vec<&'static str>,
+}
+
+impl Opening {
+    fn new(eco: String, name: String, setup: Setup, pgn: Option<String>, popularity: u32) -> Self {
+        let fen = Fen::from_setup(setup.clone()).to_string();
+        let tags = name_tags(&name);
+        Opening {
+            eco,
+            name,
+            setup,
+            fen,
+            pgn,
+            popularity,
+            tags,
+        }
+    }
+
+    pub fn eco(&self) -> &str {
+        &self.eco
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn fen(&self) -> &str {
+        &self.fen
+    }
+
+    pub fn pgn(&self) -> Option<&str> {
+        self.pgn.as_deref()
+    }
 }
 
 impl Serialize for Opening {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let mut state = serializer.serialize_struct("Opening", 3)?;
-        state.serialize_field("eco", &self.eco)?;
-        state.serialize_field("name", &self.name)?;
-        let fen = Fen::from_setup(self.setup.clone()).to_string();
-        state.serialize_field("fen", &fen)?;
+        let mut state = serializer.serialize_struct("Opening", 4)?;
+        state.serialize_field("eco", self.eco())?;
+        state.serialize_field("name", self.name())?;
+        state.serialize_field("fen", self.fen())?;
+        // lets the frontend orient the board without parsing the fen
+        state.serialize_field("turn", self.setup.turn.fold_wb("white", "black"))?;
         state.end()
     }
 }
 
+/// Serialized form of an `Opening`, which may also carry its pgn
+#[derive(Deserialize)]
+struct SerializedOpening {
+    eco: String,
+    name: String,
+    fen: String,
+    #[serde(default)]
+    pgn: Option<String>,
+    #[serde(default)]
+    popularity: u32,
+}
+
+impl<'de> Deserialize<'de> for Opening {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let opening = SerializedOpening::deserialize(deserializer)?;
+        let fen: Fen = opening.fen.parse().map_err(serde::de::Error::custom)?;
+        Ok(Opening::new(
+            opening.eco,
+            opening.name,
+            fen.into_setup(),
+            opening.pgn,
+            opening.popularity,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct OpeningMoves {
+    san: Vec<String>,
+    uci: Vec<String>,
+}
+
+/// Rules a position is played under. Only standard chess has named openings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum GameVariant {
+    #[default]
+    Standard,
+    Chess960,
+    Antichess,
+    Atomic,
+    Crazyhouse,
+    Horde,
+    KingOfTheHill,
+    RacingKings,
+    ThreeCheck,
+}
+
+/// String metric scoring how close a query is to an opening name
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SimilarityMode {
+    #[default]
+    JaroWinkler,
+    Levenshtein,
+    Sorensen,
+}
+
+impl SimilarityMode {
+    /// Similarity of two strings, between 0 and 1
+    fn similarity(self, a: &str, b: &str) -> f64 {
+        match self {
+            SimilarityMode::JaroWinkler => jaro_winkler(a, b),
+            SimilarityMode::Levenshtein => normalized_levenshtein(a, b),
+            SimilarityMode::Sorensen => sorensen_dice(a, b),
+        }
+    }
+}
+
+/// Opening of a position or of its color-flipped counterpart
+#[derive(Debug, Clone, Serialize)]
+pub struct MirroredOpening {
+    opening: Opening,
+    mirrored: bool,
+}
+
+/// Where a game left the opening book
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoveltyReport {
+    /// Last ply whose position is on a book line, 0 being the starting position
+    last_book_ply: usize,
+    /// First ply out of book, `None` if the whole game follows the book
+    novelty_ply: Option<usize>,
+    /// Deepest named opening reached before leaving the book
+    opening: Opening,
+}
+
+/// Deepest named opening a game went through
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NearestOpening {
+    opening: Opening,
+    /// Moves of the game played after reaching the opening, empty if its last
+    /// position is named
+    extra_moves: Vec<String>,
+}
+
+/// Opening name of a position, and whether its castling rights had to be guessed
+#[derive(Debug, Clone, Serialize)]
+pub struct RelaxedOpening {
+    name: String,
+    relaxed: bool,
+}
+
+/// One of the five volumes of the ECO classification
+#[derive(Debug, Clone, Serialize)]
+pub struct EcoVolume {
+    volume: String,
+    description: String,
+    /// Number of openings with a code of the volume
+    count: usize,
+}
+
+/// Parts of a name like "Sicilian Defense: Najdorf Variation, English Attack"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NameParts {
+    /// Part before the colon, the whole name if there is none
+    family: String,
+    /// First part after the colon
+    variation: Option<String>,
+    /// Comma-separated parts after the variation, from broadest to narrowest
+    sub_variations: Vec<String>,
+}
+
+/// Opening of the tree returned by `export_opening_tree`
+#[derive(Debug, Clone, Serialize)]
+pub struct OpeningNode {
+    opening: Opening,
+    /// Number of plies of the opening's line
+    depth: usize,
+    /// Index of the nearest named opening earlier on the line, if any
+    parent: Option<usize>,
+}
+
+/// Inconsistencies found in the loaded openings by `verify_opening_data`
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataReport {
+    /// `(eco, name, pgn)` rows that appear more than once
+    duplicate_entries: Vec<(String, String, String)>,
+    /// Names given to several positions
+    names_with_multiple_positions: Vec<String>,
+    /// FENs of the positions that have several names
+    positions_with_multiple_names: Vec<String>,
+    /// Names of the openings whose pgn doesn't replay to their position
+    unreplayable: Vec<String>,
+}
+
+/// Matches found so far by `search_opening_name_streamed`
+#[derive(Debug, Clone, Serialize)]
+pub struct OpeningSearchPayload {
+    id: u64,
+    openings: Vec<(Opening, f64)>,
+    finished: bool,
+}
+
 #[derive(Deserialize)]
 struct OpeningRecord {
     eco: String,
     name: String,
     pgn: String,
+    /// Optional fourth column, the number of games played with the opening
+    #[serde(default)]
+    popularity: u32,
+}
+
+impl OpeningRecord {
+    fn into_opening(self, setup: Setup) -> Opening {
+        Opening::new(self.eco, self.name, setup, Some(self.pgn), self.popularity)
+    }
 }
 
+/// Gzipped copies of the TSV files made by build.rs
 const TSV_DATA: [&[u8]; 5] = [
-    include_bytes!("../data/a.tsv"),
-    include_bytes!("../data/b.tsv"),
-    include_bytes!("../data/c.tsv"),
-    include_bytes!("../data/d.tsv"),
-    include_bytes!("../data/e.tsv"),
+    include_bytes!(concat!(env!("OUT_DIR"), "/a.tsv.gz")),
+    include_bytes!(concat!(env!("OUT_DIR"), "/b.tsv.gz")),
+    include_bytes!(concat!(env!("OUT_DIR"), "/c.tsv.gz")),
+    include_bytes!(concat!(env!("OUT_DIR"), "/d.tsv.gz")),
+    include_bytes!(concat!(env!("OUT_DIR"), "/e.tsv.gz")),
+];
+
+/// Gzipped opening table of each volume resolved by build.rs, see `read_openings_index`
+const INDEX_DATA: [&[u8]; 5] = [
+    include_bytes!(concat!(env!("OUT_DIR"), "/a.bin.gz")),
+    include_bytes!(concat!(env!("OUT_DIR"), "/b.bin.gz")),
+    include_bytes!(concat!(env!("OUT_DIR"), "/c.bin.gz")),
+    include_bytes!(concat!(env!("OUT_DIR"), "/d.bin.gz")),
+    include_bytes!(concat!(env!("OUT_DIR"), "/e.bin.gz")),
 ];
 
+/// Environment variable naming a directory of `a.tsv` to `e.tsv` files to use
+/// instead of the bundled openings
+const OPENINGS_DIR_VAR: &str = "EN_CROISSANT_OPENINGS_DIR";
+
+/// Builds the openings table and its indices ahead of time, like during the splash
+/// screen, so that the first lookup doesn't have to. Returns the number of openings.
+#[tauri::command]
+pub async fn preload_openings() -> Result<usize, Error> {
+    lazy_static::initialize(&OPENINGS);
+    lazy_static::initialize(&OPENINGS_BY_HASH);
+    lazy_static::initialize(&OPENINGS_BY_NAME);
+    lazy_static::initialize(&SEARCH_NAMES);
+    lazy_static::initialize(&SORTED_SEARCH_NAMES);
+    lazy_static::initialize(&CANONICAL_INDICES);
+    Ok(OPENINGS.len())
+}
+
+/// Returns a short hash of the bundled TSV files, which changes whenever the
+/// opening data does
+#[tauri::command]
+pub fn opening_dataset_version() -> String {
+    DATASET_VERSION.clone()
+}
+
+fn dataset_version<'a>(tables: impl IntoIterator<Item = &'a [u8]>) -> String {
+    let mut hasher = Sha256::new();
+    for table in tables {
+        hasher.update(table);
+    }
+    let hash = format!("{:x}", hasher.finalize());
+    hash[..16].to_string()
+}
+
+/// Entry of the prebuilt openings index, must stay in sync with build.rs
+#[derive(Decode)]
+struct IndexedOpening {
+    eco: String,
+    name: String,
+    fen: String,
+    pgn: String,
+    popularity: u32,
+}
+
 #[tauri::command]
 #[specta::specta]
-pub fn get_opening_from_fen(fen: &str) -> Result<String, Error> {
-    let fen: Fen = fen.parse()?;
-    get_opening_from_setup(fen.into_setup())
+pub fn get_opening_from_fen(fen: &str, variant: Option<GameVariant>) -> Result<String, Error> {
+    let fen = parse_fen(fen)?;
+    get_opening_from_setup(fen.into_setup(), variant.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn get_full_opening_from_fen(fen: &str) -> Result<Opening, Error> {
+    let fen = parse_fen(fen)?;
+    get_full_opening_from_setup(fen.into_setup())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn get_opening_from_name(name: &str) -> Result<String, Error> {
-    OPENINGS
-        .iter()
-        .find(|o| o.name == name)
-        .map(|o| o.pgn.clone().expect("opening without pgn"))
-        .ok_or_else(|| Error::NoOpeningFound)
+    match find_by_name(name) {
+        Ok(opening) => Ok(opening.pgn.unwrap_or_default()),
+        Err(Error::NoOpeningFound) => Err(did_you_mean(name)),
+        Err(e) => Err(e),
+    }
 }
 
-pub fn get_opening_from_setup(setup: Setup) -> Result<String, Error> {
-    OPENINGS
+/// Returns the pgn of each named opening, `None` for unknown names and the
+/// positions without moves, so that many names are resolved in one call
+#[tauri::command]
+pub fn get_openings_from_names(names: Vec<String>) -> Result<Vec<Option<String>>, Error> {
+    Ok(names
         .iter()
-        .find(|o| o.setup == setup)
-        .map(|o| o.name.clone())
-        .ok_or_else(|| Error::NoOpeningFound)
+        .map(|name| find_by_name(name).ok().and_then(|o| o.pgn))
+        .collect())
+}
+
+/// `Error::NoOpeningFound`, suggesting the closest name, bundled or loaded by the
+/// user, when one is close enough to be a misspelling of the given name
+fn did_you_mean(name: &str) -> Error {
+    find_best_matches(name, 1, DID_YOU_MEAN_MIN_SCORE)
+        .into_iter()
+        .next()
+        .map_or(Error::NoOpeningFound, |opening| {
+            Error::NoOpeningFoundDidYouMean {
+                suggestion: opening.name,
+            }
+        })
 }
 
+/// Returns the FEN of the position of the named opening
 #[tauri::command]
-pub async fn search_opening_name(query: String) -> Result<Vec<Opening>, Error> {
-    let mut best_matches: Vec<(Opening, f64)> = Vec::new();
+#[specta::specta]
+pub fn get_fen_from_opening_name(name: &str) -> Result<String, Error> {
+    find_by_name(name).map(|o| o.fen().to_string())
+}
 
-    for opening in OPENINGS.iter() {
-        if best_matches.iter().any(|(m, _)| m.name == opening.name) {
-            continue;
-        }
+/// Returns the FEN of every position along the line of the named opening, from
+/// the starting position to its own. The extra positions only have their own.
+#[tauri::command]
+pub fn opening_line_fens(name: String) -> Result<Vec<String>, Error> {
+    let opening = find_by_name(&name)?;
+    let Some(pgn) = opening.pgn() else {
+        return Ok(vec![opening.fen().to_string()]);
+    };
+    let render = |pos: &Chess| Fen::from_position(pos.clone(), EnPassantMode::Legal).to_string();
+    let mut fens = vec![render(&Chess::default())];
+    replay_pgn(pgn, |pos| fens.push(render(pos)))?;
+    Ok(fens)
+}
 
-        let score = jaro_winkler(&query, &opening.name);
+#[tauri::command]
+pub fn get_opening_moves(name: &str) -> Result<OpeningMoves, Error> {
+    let opening = find_by_name(name)?;
 
-        if best_matches.len() < 15 {
-            best_matches.push((opening.clone(), score));
-            best_matches.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-        } else if let Some(min_score) = best_matches.last().map(|(_, s)| *s) {
-            if score > min_score {
-                best_matches.pop();
-                best_matches.push((opening.clone(), score));
-                best_matches.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-            }
+    let mut moves = OpeningMoves::default();
+    if let Some(pgn) = opening.pgn() {
+        let mut pos = Chess::default();
+        for m in pgn_moves(pgn)? {
+            moves.uci.push(m.to_uci(CastlingMode::Standard).to_string());
+            let san = SanPlus::from_move_and_play_unchecked(&mut pos, &m);
+            moves.san.push(san.to_string());
         }
     }
+    Ok(moves)
+}
 
-    if !best_matches.is_empty() {
-        let best_matches_names = best_matches.iter().map(|(o, _)| o.clone()).collect();
-        Ok(best_matches_names)
-    } else {
-        Err(Error::NoMatchFound)
+/// Returns the openings whose line extends the given opening by exactly one move
+#[tauri::command]
+pub fn get_opening_children(name: &str) -> Result<Vec<Opening>, Error> {
+    let opening = find_by_name(name)?;
+    let line = opening_line(&opening);
+
+    Ok(OPENINGS
+        .iter()
+        .copied()
+        .filter(|o| {
+            let other = opening_line(o);
+            other.len() == line.len() + 1 && other.starts_with(&line)
+        })
+        .cloned()
+        .collect())
+}
+
+/// Returns the given opening followed by the openings whose line extends it by
+/// at most `max_depth` moves, for expanding the tree a few levels at a time
+#[tauri::command]
+pub fn opening_subtree(root_name: String, max_depth: usize) -> Result<Vec<Opening>, Error> {
+    let root = find_by_name(&root_name)?;
+    let line = opening_line(&root);
+
+    let mut openings = vec![root.clone()];
+    openings.extend(
+        OPENINGS
+            .iter()
+            .copied()
+            .filter(|o| o.name != root.name && o.pgn().is_some())
+            .filter(|o| {
+                let other = opening_line(o);
+                other.len() <= line.len() + max_depth && other.starts_with(&line)
+            })
+            .cloned(),
+    );
+    Ok(openings)
+}
+
+/// Returns the deepest named opening on the moves two openings start with, or
+/// `None` if they already differ on the first move
+#[tauri::command]
+pub fn common_opening_ancestor(a: String, b: String) -> Result<Option<Opening>, Error> {
+    let a = find_by_name(&a)?;
+    let b = find_by_name(&b)?;
+    let (a, b) = (opening_line(&a), opening_line(&b));
+    let shared: Vec<String> = a
+        .iter()
+        .zip(b.iter())
+        .take_while(|(a, b)| a == b)
+        .map(|(mv, _)| mv.to_string())
+        .collect();
+    let ancestor = deepest_opening(&shared, parse_game_move)?;
+    Ok(Some(ancestor).filter(|o| o.eco != "Extra"))
+}
+
+/// Returns the named openings whose line starts with the given first move, by ECO code
+#[tauri::command]
+pub fn openings_after_first_move(san: String) -> Result<Vec<Opening>, Error> {
+    let start = Chess::default();
+    let first = parse_game_move(&start, 1, &san)?;
+
+    let mut openings: Vec<Opening> = OPENINGS
+        .iter()
+        .copied()
+        .filter(|o| {
+            opening_line(o)
+                .first()
+                .and_then(|token| token.parse::<San>().ok())
+                .and_then(|token| token.to_move(&start).ok())
+                .is_some_and(|m| m == first)
+        })
+        .cloned()
+        .collect();
+    openings.sort_by(|a, b| a.eco.cmp(&b.eco));
+    Ok(openings)
+}
+
+/// Picks a named opening at random among those of at least `min_depth` plies
+/// and, if given, of one ECO volume. The same `seed` always picks the same opening.
+#[tauri::command]
+pub fn random_opening(
+    min_depth: Option<usize>,
+    eco_prefix: Option<char>,
+    seed: Option<u64>,
+) -> Result<Opening, Error> {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let candidates: Vec<&Opening> = OPENINGS
+        .iter()
+        .copied()
+        .filter(|o| o.eco != "Extra")
+        .filter(|o| match eco_prefix {
+            Some(volume) => o.eco.starts_with(volume.to_ascii_uppercase()),
+            None => true,
+        })
+        .filter(|o| opening_line(o).len() >= min_depth.unwrap_or(0))
+        .collect();
+    candidates
+        .choose(&mut rng)
+        .map(|o| (*o).clone())
+        .ok_or(Error::NoOpeningFound)
+}
+
+/// Fewest plies of the openings picked as the opening of the day, so that it's
+/// a proper line rather than a first move
+const OPENING_OF_THE_DAY_MIN_DEPTH: usize = 8;
+
+/// Picks the opening of the day of a `YYYY-MM-DD` date from its hash, so that every
+/// client shows the same opening on the same day
+#[tauri::command]
+pub fn opening_of_the_day(date: String) -> Result<Opening, Error> {
+    let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d")?;
+    let candidates: Vec<&Opening> = OPENINGS
+        .iter()
+        .copied()
+        .filter(|o| o.eco != "Extra" && opening_line(o).len() >= OPENING_OF_THE_DAY_MIN_DEPTH)
+        .collect();
+    if candidates.is_empty() {
+        return Err(Error::NoOpeningFound);
     }
+    let hash = Sha256::digest(date.format("%Y-%m-%d").to_string());
+    let index = u64::from_be_bytes(hash[..8].try_into().unwrap()) % candidates.len() as u64;
+    Ok(candidates[index as usize].clone())
 }
 
-lazy_static! {
-    static ref OPENINGS: Vec<Opening> = {
-        info!("Initializing openings table...");
+/// Returns the openings reaching the same pawns as a position, wherever its other
+/// pieces stand, in table order
+#[tauri::command]
+pub fn openings_with_pawn_structure(fen: String) -> Result<Vec<Opening>, Error> {
+    let setup = parse_fen(&fen)?.into_setup();
+    let structure = pawn_structure(&setup.board);
+    Ok(OPENINGS
+        .iter()
+        .copied()
+        .filter(|o| pawn_structure(&o.setup.board) == structure)
+        .cloned()
+        .collect())
+}
 
-        let mut positions = vec![
-            Opening {
-                eco: "Extra".to_string(),
-                name: "Starting Position".to_string(),
-                setup: Setup::default(),
-                pgn: None,
-            },
-            Opening {
-                eco: "Extra".to_string(),
-                name: "Empty Board".to_string(),
-                setup: Setup::empty(),
-                pgn: None,
-            },
-        ];
+/// White and black pawns of a board
+fn pawn_structure(board: &Board) -> (Bitboard, Bitboard) {
+    (board.pawns() & board.white(), board.pawns() & board.black())
+}
 
-        for tsv in TSV_DATA {
-            let mut rdr = csv::ReaderBuilder::new().delimiter(b'\t').from_reader(tsv);
-            for result in rdr.deserialize() {
-                let record: OpeningRecord = result.expect("Failed to deserialize opening");
-                let mut pos = Chess::default();
-                for token in record.pgn.split_whitespace() {
-                    if let Ok(san) = token.parse::<San>() {
-                        pos.play_unchecked(&san.to_move(&pos).expect("legal move"));
-                    }
+/// Returns the openings before and after the named one among those sharing its
+/// ECO code, ordered by name
+#[tauri::command]
+pub fn opening_siblings(name: String) -> Result<(Option<Opening>, Option<Opening>), Error> {
+    let opening = find_by_name(&name)?;
+    let mut siblings: BTreeMap<&str, &Opening> = BTreeMap::new();
+    for o in OPENINGS.iter().copied().filter(|o| o.eco == opening.eco) {
+        siblings.entry(o.name.as_str()).or_insert(o);
+    }
+
+    let prev = siblings
+        .range::<str, _>(..opening.name.as_str())
+        .next_back()
+        .map(|(_, o)| (*o).clone());
+    let next = siblings
+        .range::<str, _>((Bound::Excluded(opening.name.as_str()), Bound::Unbounded))
+        .next()
+        .map(|(_, o)| (*o).clone());
+    Ok((prev, next))
+}
+
+/// Returns the distinct ECO codes, in order, of the openings whose name starts with
+/// the given family, like "B20" to "B99" and "C10" for "Sicilian Defense"
+#[tauri::command]
+pub fn eco_codes_for_name(name: String) -> Result<Vec<String>, Error> {
+    let family = canonical_name(&name);
+    let codes: BTreeSet<&str> = OPENINGS
+        .iter()
+        .copied()
+        .filter(|o| canonical_name(&o.name).starts_with(&family))
+        .map(|o| o.eco.as_str())
+        .collect();
+    if codes.is_empty() {
+        return Err(Error::NoOpeningFound);
+    }
+    Ok(codes.into_iter().map(String::from).collect())
+}
+
+/// Returns the named openings one move away from a named position, with the
+/// move leading to each of them, most popular first. Positions out of book have none.
+#[tauri::command]
+pub fn suggest_next_opening_moves(fen: String) -> Result<Vec<(String, Opening)>, Error> {
+    let fen = parse_fen(&fen)?;
+    let pos = Chess::from_setup(fen.into_setup(), CastlingMode::Standard)
+        .map_err(|e| position_error(e.kinds()))?;
+    if get_full_opening_from_setup(pos.clone().into_setup(EnPassantMode::Legal)).is_err() {
+        return Ok(Vec::new());
+    }
+
+    let mut moves: Vec<(String, Opening)> = pos
+        .legal_moves()
+        .into_iter()
+        .filter_map(|m| {
+            let san = San::from_move(&pos, &m).to_string();
+            let mut after = pos.clone();
+            after.play_unchecked(&m);
+            let opening =
+                get_full_opening_from_setup(after.into_setup(EnPassantMode::Legal)).ok()?;
+            Some((san, opening))
+        })
+        .collect();
+    moves.sort_by(|(a, x), (b, y)| y.popularity.cmp(&x.popularity).then_with(|| a.cmp(b)));
+    Ok(moves)
+}
+
+/// Returns how many plies the line of the opening has, 0 for the extra positions
+#[tauri::command]
+pub fn opening_depth(name: String) -> Result<usize, Error> {
+    find_by_name(&name).map(|o| opening_line(&o).len())
+}
+
+/// Returns the named opening one ply before the given one, if there is any
+#[tauri::command]
+pub fn get_opening_parent(name: &str) -> Result<Option<Opening>, Error> {
+    let opening = find_by_name(name)?;
+    let line = opening_line(&opening);
+    if line.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(setup_from_pgn(&line[..line.len() - 1].join(" "))
+        .ok()
+        .and_then(|setup| get_full_opening_from_setup(setup).ok()))
+}
+
+/// Returns the move that takes the parent opening to the child opening, when
+/// the child's line extends the parent's by exactly one move
+#[tauri::command]
+pub fn opening_transition_move(parent: String, child: String) -> Result<String, Error> {
+    let parent = find_by_name(&parent)?;
+    let child = find_by_name(&child)?;
+    let parent_line = opening_line(&parent);
+    let child_line = opening_line(&child);
+
+    // the empty board has no line but isn't the starting position either
+    let parent_reached = setup_from_pgn(&parent_line.join(" "))
+        .is_ok_and(|setup| normalize_setup(setup) == normalize_setup(parent.setup.clone()));
+    match child_line.split_last() {
+        Some((mv, line)) if parent_reached && line == parent_line.as_slice() => Ok(mv.to_string()),
+        _ => Err(Error::NotAContinuation),
+    }
+}
+
+/// Serializes the openings of the ECO volumes as a JSON array, for the frontend to
+/// bundle. Openings also have their pgn when `include_pgn` is set.
+#[tauri::command]
+pub fn export_openings_json(include_pgn: Option<bool>) -> Result<String, Error> {
+    let openings = OPENINGS.iter().copied().filter(|o| o.eco != "Extra");
+    if !include_pgn.unwrap_or(false) {
+        return Ok(serde_json::to_string(&openings.collect::<Vec<_>>())?);
+    }
+    let openings = openings
+        .map(|o| {
+            let mut value = serde_json::to_value(o)?;
+            value["pgn"] = o.pgn().into();
+            Ok(value)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(serde_json::to_string(&openings)?)
+}
+
+/// Returns every opening along with the index of its parent, the nearest named
+/// position on the way to it, so the whole tree can be built in one call
+#[tauri::command]
+pub fn export_opening_tree() -> Result<Vec<OpeningNode>, Error> {
+    Ok(OPENINGS
+        .iter()
+        .copied()
+        .map(|opening| {
+            let line = opening_line(opening);
+            let mut setups = vec![Setup::default()];
+            let _ = replay_pgn(&line.join(" "), |pos| {
+                setups.push(normalize_setup(
+                    pos.clone().into_setup(EnPassantMode::Legal),
+                ))
+            });
+            let depth = line.len();
+
+            let parent = (0..depth).rev().find_map(|ply| {
+                let candidates = openings_by_setup(&setups[ply]);
+                let on_line = candidates
+                    .iter()
+                    .find(|&&i| opening_line(OPENINGS[i]) == line[..ply]);
+                on_line
+                    .or_else(|| {
+                        candidates
+                            .iter()
+                            .find(|&&i| opening_line(OPENINGS[i]).len() < depth)
+                    })
+                    .copied()
+            });
+            OpeningNode {
+                opening: opening.clone(),
+                depth,
+                parent,
+            }
+        })
+        .collect())
+}
+
+/// Returns the name the table uses for an opening, ignoring case and accents and
+/// translating known aliases, like "Spanish Game: Exchange Variation" to
+/// "Ruy Lopez: Exchange Variation"
+#[tauri::command]
+pub fn resolve_opening_alias(query: String) -> Result<String, Error> {
+    let query = normalize_name(query.trim());
+    let find = |name: &str| {
+        OPENINGS_BY_NAME
+            .values()
+            .find(|&&i| SEARCH_NAMES[i] == name)
+            .map(|&i| OPENINGS[i].name.clone())
+    };
+    if let Some(name) = find(&query) {
+        return Ok(name);
+    }
+
+    let (family, variation) = match query.split_once(':') {
+        Some((family, variation)) => (family.trim(), Some(variation)),
+        None => (query.as_str(), None),
+    };
+    let (_, canonical) = OPENING_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == family)
+        .ok_or(Error::NoOpeningFound)?;
+    variation
+        .and_then(|variation| find(&format!("{}:{}", normalize_name(canonical), variation)))
+        .or_else(|| find(&normalize_name(canonical)))
+        .ok_or(Error::NoOpeningFound)
+}
+
+/// Returns the opening with this name, looking at the user openings first
+fn find_by_name(name: &str) -> Result<Opening, Error> {
+    USER_OPENINGS
+        .read()
+        .unwrap()
+        .find_by_name(name)
+        .cloned()
+        .ok_or(Error::NoOpeningFound)
+}
+
+/// Collapses runs of whitespace and turns dash variants into hyphens, so that
+/// exact name lookups don't depend on the typography of the name
+fn canonical_name(name: &str) -> String {
+    name.split_whitespace()
+        .map(|word| word.replace(['\u{2010}', '\u{2011}', '\u{2013}', '\u{2014}'], "-"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Maps each canonical name to the index of its canonical entry. When several lines
+/// share a name, the shortest one is kept, or the first one if they have the same length.
+fn index_names(openings: &[&Opening]) -> HashMap<String, usize> {
+    let mut index: HashMap<String, usize> = HashMap::with_capacity(openings.len());
+    let mut collisions = 0;
+    for (i, opening) in openings.iter().enumerate() {
+        let name = canonical_name(&opening.name);
+        match index.get_mut(&name) {
+            Some(kept) => {
+                collisions += 1;
+                if opening_line(opening).len() < opening_line(&openings[*kept]).len() {
+                    *kept = i;
                 }
-                positions.push(Opening {
-                    eco: record.eco,
-                    name: record.name,
-                    setup: pos.into_setup(EnPassantMode::Legal),
-                    pgn: Some(record.pgn),
-                });
+            }
+            None => {
+                index.insert(name, i);
             }
         }
-        positions
+    }
+    if collisions > 0 {
+        info!("{} openings share their name with another line", collisions);
+    }
+    index
+}
+
+/// Returns the moves of an opening's pgn, read by `pgn_moves` and written as SAN
+fn opening_line(opening: &Opening) -> Vec<String> {
+    let moves = opening.pgn().and_then(|pgn| pgn_moves(pgn).ok());
+    let mut pos = Chess::default();
+    moves
+        .unwrap_or_default()
+        .iter()
+        .map(|m| SanPlus::from_move_and_play_unchecked(&mut pos, m).to_string())
+        .collect()
+}
+
+/// Returns the name of the opening, or `Error::PositionNotNamed` right away for
+/// variants since the table only covers standard chess
+pub fn get_opening_from_setup(setup: Setup, variant: GameVariant) -> Result<String, Error> {
+    if variant != GameVariant::Standard {
+        return Err(Error::PositionNotNamed);
+    }
+    get_full_opening_from_setup(setup).map(|o| o.name)
+}
+
+/// Like `get_opening_from_fen`, but when the position isn't named, tries again
+/// with the castling rights of kings and rooks still on their starting squares,
+/// since pasted FENs often get them wrong
+#[tauri::command]
+pub fn get_opening_from_fen_relaxed(fen: &str) -> Result<RelaxedOpening, Error> {
+    let setup = parse_fen(fen)?.into_setup();
+    match get_opening_from_setup(setup.clone(), GameVariant::Standard) {
+        Ok(name) => Ok(RelaxedOpening {
+            name,
+            relaxed: false,
+        }),
+        Err(Error::PositionNotNamed) => {
+            let castling_rights = home_castling_rights(&setup.board);
+            if castling_rights == setup.castling_rights {
+                return Err(Error::PositionNotNamed);
+            }
+            let mut relaxed = setup;
+            relaxed.castling_rights = castling_rights;
+            get_opening_from_setup(relaxed, GameVariant::Standard).map(|name| RelaxedOpening {
+                name,
+                relaxed: true,
+            })
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Castling rights of a position whose kings and rooks on their starting
+/// squares never moved
+fn home_castling_rights(board: &Board) -> Bitboard {
+    let mut rights = Bitboard::EMPTY;
+    for (color, king, rooks) in [
+        (Color::White, Square::E1, [Square::A1, Square::H1]),
+        (Color::Black, Square::E8, [Square::A8, Square::H8]),
+    ] {
+        if board.king_of(color) != Some(king) {
+            continue;
+        }
+        for rook in rooks {
+            if board.piece_at(rook)
+                == Some(Piece {
+                    color,
+                    role: Role::Rook,
+                })
+            {
+                rights.add(rook);
+            }
+        }
+    }
+    rights
+}
+
+fn parse_fen(fen: &str) -> Result<Fen, Error> {
+    fen.parse().map_err(|e: ParseFenError| Error::InvalidFen {
+        input: fen.to_string(),
+        reason: e.to_string(),
+    })
+}
+
+/// Returns the opening of a position, ignoring its move counters
+pub fn get_full_opening_from_setup(setup: Setup) -> Result<Opening, Error> {
+    USER_OPENINGS
+        .read()
+        .unwrap()
+        .find_by_setup(&normalize_setup(setup))
+        .cloned()
+        .ok_or(Error::PositionNotNamed)
+}
+
+/// Returns the opening of a position given by its 64-bit Zobrist hash, the
+/// same one polyglot books key positions by. The hash is a string, in hex with
+/// a `0x` prefix or in decimal, since JavaScript numbers can't hold every hash.
+#[tauri::command]
+pub fn get_opening_from_hash(hash: String) -> Result<Opening, Error> {
+    let hash = match hash.trim().strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16)?,
+        None => hash.trim().parse()?,
     };
+    OPENINGS_BY_HASH
+        .get(&hash)
+        .map(|&i| OPENINGS[i].clone())
+        .ok_or(Error::PositionNotNamed)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Zobrist hash of a position, which only a legal position has
+fn position_hash(setup: Setup) -> Option<u64> {
+    Chess::from_setup(setup, CastlingMode::Standard)
+        .or_else(PositionError::ignore_invalid_ep_square)
+        .ok()
+        .map(|pos| polyglot_key(&pos))
+}
 
-    #[test]
-    fn test_get_opening() {
-        let opening =
-            get_opening_from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 1 2")
-                .unwrap();
-        assert_eq!(opening, "Bongcloud Attack");
+/// Returns every opening with this position, sorted by ECO code
+#[tauri::command]
+pub fn get_all_openings_from_fen(fen: &str) -> Result<Vec<Opening>, Error> {
+    let fen = parse_fen(fen)?;
+    let mut openings: Vec<Opening> = USER_OPENINGS
+        .read()
+        .unwrap()
+        .find_all_by_setup(&normalize_setup(fen.into_setup()))
+        .into_iter()
+        .cloned()
+        .collect();
+    if openings.is_empty() {
+        return Err(Error::PositionNotNamed);
+    }
+    openings.sort_by(|a, b| a.eco.cmp(&b.eco));
+    Ok(openings)
+}
+
+/// Returns the opening of the position with the shortest line, which is the name
+/// introducing it rather than a deeper variation reaching it by transposition
+#[tauri::command]
+pub fn shortest_named_line_for_fen(fen: String) -> Result<Opening, Error> {
+    let fen = parse_fen(&fen)?;
+    let user = USER_OPENINGS.read().unwrap();
+    shortest_line(user.find_all_by_setup(&normalize_setup(fen.into_setup())))
+        .cloned()
+        .ok_or(Error::PositionNotNamed)
+}
+
+/// Looks up an opening by the piece placement field of a FEN and the side to move,
+/// `w` or `b`, ignoring castling rights and en passant. Since several openings may
+/// then match, like lines that only differ by a king move and back, the one with
+/// the shortest line is returned, a user opening winning a tie.
+#[tauri::command]
+pub fn get_opening_from_board_placement(placement: String, turn: String) -> Result<Opening, Error> {
+    let turn = match turn.as_str() {
+        "white" => "w",
+        "black" => "b",
+        turn => turn,
+    };
+    let setup = parse_fen(&format!("{} {} - - 0 1", placement, turn))?.into_setup();
+    let user = USER_OPENINGS.read().unwrap();
+    user.find_by_board(&setup.board, setup.turn)
+        .cloned()
+        .ok_or(Error::PositionNotNamed)
+}
+
+/// Indices into `OPENINGS` of every entry with this normalized position, in table order
+fn openings_by_setup(setup: &Setup) -> Vec<usize> {
+    // the extra positions are already normalized
+    let mut indices: Vec<usize> = (0..EXTRA_OPENINGS.len())
+        .filter(|&i| EXTRA_OPENINGS[i].setup == *setup)
+        .collect();
+    indices.extend(VOLUMES.indices_by_setup(setup));
+    indices
+}
+
+/// Returns the opening with the fewest moves, the first one of them on ties
+fn shortest_line<'a>(openings: impl IntoIterator<Item = &'a Opening>) -> Option<&'a Opening> {
+    openings.into_iter().min_by_key(|o| opening_line(o).len())
+}
+
+/// Shortens a name to at most `max_chars` characters, ending with an ellipsis if
+/// it had to be cut. Accents are kept with the letter they follow.
+#[tauri::command]
+pub fn truncate_opening_name(name: String, max_chars: usize) -> String {
+    if name.chars().count() <= max_chars {
+        return name;
+    }
+    let Some(kept) = max_chars.checked_sub(1) else {
+        return String::new();
+    };
+    let mut prefix = char_prefix(&name, kept);
+    while !prefix.is_empty() && name[prefix.len()..].starts_with(is_combining_mark) {
+        let (last, _) = prefix.char_indices().last().unwrap();
+        prefix = &prefix[..last];
+    }
+    format!("{}…", prefix.trim_end())
+}
+
+/// Returns the other names of the position of the named opening, the user ones
+/// first and then in table order, for linking the entries that reach the same
+/// position under different names
+#[tauri::command]
+pub fn opening_synonyms(name: String) -> Result<Vec<String>, Error> {
+    let opening = find_by_name(&name)?;
+    let user = USER_OPENINGS.read().unwrap();
+    let openings = user.find_all_by_setup(&normalize_setup(opening.setup.clone()));
+    if openings.is_empty() {
+        return Err(Error::PositionNotNamed);
+    }
+    Ok(other_names(&opening.name, openings))
+}
+
+/// Distinct names of the openings other than the given one
+fn other_names<'a>(name: &str, openings: impl IntoIterator<Item = &'a Opening>) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for opening in openings {
+        if opening.name != name && !names.contains(&opening.name) {
+            names.push(opening.name.clone());
+        }
+    }
+    names
+}
+
+/// Looks up the opening of each FEN in one call, with `None` for the positions
+/// that have no opening or can't be parsed
+#[tauri::command]
+pub fn classify_positions(fens: Vec<String>) -> Result<Vec<Option<Opening>>, Error> {
+    Ok(fens
+        .iter()
+        .map(|fen| {
+            let fen: Fen = fen.parse().ok()?;
+            get_full_opening_from_setup(fen.into_setup()).ok()
+        })
+        .collect())
+}
+
+/// Whether the position is the one of a named opening of the ECO volumes.
+/// The extra positions aren't openings, so the starting position isn't in book.
+#[tauri::command]
+pub fn is_in_opening_book(fen: String) -> Result<bool, Error> {
+    let fen = parse_fen(&fen)?;
+    Ok(is_book_setup(fen.into_setup()))
+}
+
+/// Whether playing a SAN move in the position leads to a named opening of the
+/// ECO volumes, for checking if a candidate move is still theory
+#[tauri::command]
+pub fn is_book_move(fen: String, san: String) -> Result<bool, Error> {
+    let fen = parse_fen(&fen)?;
+    let mut pos = Chess::from_setup(fen.into_setup(), CastlingMode::Standard)
+        .map_err(|e| position_error(e.kinds()))?;
+    let m = san.parse::<SanPlus>()?.san.to_move(&pos)?;
+    pos.play_unchecked(&m);
+    Ok(is_book_setup(pos.into_setup(EnPassantMode::Legal)))
+}
+
+fn is_book_setup(setup: Setup) -> bool {
+    USER_OPENINGS
+        .read()
+        .unwrap()
+        .find_all_by_setup(&normalize_setup(setup))
+        .iter()
+        .any(|o| o.eco != "Extra")
+}
+
+/// Checks that a FEN is well formed and describes a legal standard chess position
+#[tauri::command]
+pub fn validate_fen(fen: String) -> Result<(), Error> {
+    let fen = parse_fen(&fen)?;
+    match Chess::from_setup(fen.into_setup(), CastlingMode::Standard) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(position_error(e.kinds())),
+    }
+}
+
+/// Picks the most fundamental problem of an illegal position
+fn position_error(kinds: PositionErrorKinds) -> Error {
+    if kinds.intersects(PositionErrorKinds::MISSING_KING | PositionErrorKinds::TOO_MANY_KINGS) {
+        Error::InvalidKingCount
+    } else if kinds.contains(PositionErrorKinds::PAWNS_ON_BACKRANK) {
+        Error::PawnsOnBackRank
+    } else if kinds.contains(PositionErrorKinds::TOO_MUCH_MATERIAL) {
+        Error::TooMuchMaterial
+    } else if kinds.contains(PositionErrorKinds::INVALID_CASTLING_RIGHTS) {
+        Error::InvalidCastlingRights
+    } else if kinds.contains(PositionErrorKinds::INVALID_EP_SQUARE) {
+        Error::InvalidEpSquare
+    } else if kinds.contains(PositionErrorKinds::OPPOSITE_CHECK) {
+        Error::OppositeCheck
+    } else if kinds.contains(PositionErrorKinds::IMPOSSIBLE_CHECK) {
+        Error::ImpossibleCheck
+    } else {
+        Error::InvalidPosition(kinds)
+    }
+}
+
+/// Looks up an opening after dropping en passant squares that can't be used,
+/// so that positions reached through a different move order still resolve to the same entry.
+#[tauri::command]
+pub fn get_opening_by_transposition(fen: &str) -> Result<Opening, Error> {
+    let fen = parse_fen(fen)?;
+    let pos = Chess::from_setup(fen.into_setup(), CastlingMode::Standard)?;
+    get_full_opening_from_setup(pos.into_setup(EnPassantMode::Legal))
+}
+
+/// Looks up an opening like `get_opening_by_transposition`, falling back to the
+/// position with colors swapped, e.g. to recognize a white setup played by black.
+#[tauri::command]
+pub fn get_opening_from_mirrored_fen(fen: &str) -> Result<MirroredOpening, Error> {
+    let fen = parse_fen(fen)?;
+    let pos = Chess::from_setup(fen.into_setup(), CastlingMode::Standard)?;
+    let setup = pos.into_setup(EnPassantMode::Legal);
+    if let Ok(opening) = get_full_opening_from_setup(setup.clone()) {
+        return Ok(MirroredOpening {
+            opening,
+            mirrored: false,
+        });
+    }
+    get_full_opening_from_setup(mirror_setup(setup)).map(|opening| MirroredOpening {
+        opening,
+        mirrored: true,
+    })
+}
+
+/// Flips the board vertically and swaps the colors of the pieces and the side to move
+fn mirror_setup(setup: Setup) -> Setup {
+    let mut board = Board::empty();
+    for (square, piece) in setup.board.iter() {
+        board.set_piece_at(
+            square.flip_vertical(),
+            Piece {
+                color: !piece.color,
+                role: piece.role,
+            },
+        );
+    }
+    Setup {
+        board,
+        promoted: setup.promoted.flip_vertical(),
+        turn: !setup.turn,
+        castling_rights: setup.castling_rights.flip_vertical(),
+        ep_square: setup.ep_square.map(Square::flip_vertical),
+        ..setup
+    }
+}
+
+/// Replays the given SAN moves from the starting position and returns the
+/// deepest named opening reached along the way.
+#[tauri::command]
+pub fn get_deepest_opening(moves: Vec<String>) -> Result<Opening, Error> {
+    deepest_opening(&moves, parse_game_move)
+}
+
+/// Like `get_deepest_opening`, but with moves in UCI notation, like "e2e4" or "e7e8q"
+#[tauri::command]
+pub fn get_opening_from_uci(moves: Vec<String>) -> Result<Opening, Error> {
+    deepest_opening(&moves, parse_uci_move)
+}
+
+/// Returns the ECO code of the deepest named opening the given SAN moves reach,
+/// which the later positions inherit once the game leaves book
+#[tauri::command]
+pub fn eco_for_game(moves: Vec<String>) -> Result<String, Error> {
+    let opening = deepest_opening(&moves, parse_game_move)?;
+    if opening.eco == "Extra" {
+        return Err(Error::PositionNotNamed);
+    }
+    Ok(opening.eco)
+}
+
+fn deepest_opening(
+    moves: &[String],
+    parse_move: impl Fn(&Chess, usize, &str) -> Result<Move, Error>,
+) -> Result<Opening, Error> {
+    let mut pos = Chess::default();
+    let mut deepest = get_full_opening_from_setup(pos.clone().into_setup(EnPassantMode::Legal))?;
+    for (i, mv) in moves.iter().enumerate() {
+        let m = parse_move(&pos, i + 1, mv)?;
+        pos.play_unchecked(&m);
+        if let Ok(opening) =
+            get_full_opening_from_setup(pos.clone().into_setup(EnPassantMode::Legal))
+        {
+            deepest = opening;
+        }
+    }
+    Ok(deepest)
+}
+
+/// Replays the first `ply` of the given SAN moves from the starting position and
+/// returns the opening of the position reached, if it has one
+#[tauri::command]
+pub fn get_opening_from_moves_truncated(
+    moves: Vec<String>,
+    ply: usize,
+) -> Result<Option<Opening>, Error> {
+    if ply > moves.len() {
+        return Err(Error::PlyOutOfRange(ply, moves.len()));
+    }
+    let mut pos = Chess::default();
+    for (i, mv) in moves[..ply].iter().enumerate() {
+        let m = parse_game_move(&pos, i + 1, mv)?;
+        pos.play_unchecked(&m);
+    }
+    Ok(get_full_opening_from_setup(pos.into_setup(EnPassantMode::Legal)).ok())
+}
+
+/// Replays the moves of a PGN movetext, like `1. e4 e5 2. Nf3 1-0`, and returns
+/// the deepest named opening reached along the way.
+#[tauri::command]
+pub fn get_opening_from_pgn(movetext: String) -> Result<Opening, Error> {
+    get_deepest_opening(movetext_moves(&movetext))
+}
+
+/// Replays the given SAN moves from the starting position and returns the
+/// opening of the position after each of them, if it has one.
+#[tauri::command]
+pub fn classify_game(moves: Vec<String>) -> Result<Vec<Option<Opening>>, Error> {
+    let mut pos = Chess::default();
+    let mut openings = Vec::with_capacity(moves.len());
+    for (i, mv) in moves.iter().enumerate() {
+        let m = parse_game_move(&pos, i + 1, mv)?;
+        pos.play_unchecked(&m);
+        openings
+            .push(get_full_opening_from_setup(pos.clone().into_setup(EnPassantMode::Legal)).ok());
+    }
+    Ok(openings)
+}
+
+/// Replays the given SAN moves from the starting position and reports the ply
+/// where the game left the positions covered by the opening book, including the
+/// lines of the user openings.
+#[tauri::command]
+pub fn find_novelty(moves: Vec<String>) -> Result<NoveltyReport, Error> {
+    let mut pos = Chess::default();
+    let mut opening = get_full_opening_from_setup(pos.clone().into_setup(EnPassantMode::Legal))?;
+    for (i, mv) in moves.iter().enumerate() {
+        let m = parse_game_move(&pos, i + 1, mv)?;
+        pos.play_unchecked(&m);
+        let setup = normalize_setup(pos.clone().into_setup(EnPassantMode::Legal));
+        if !USER_OPENINGS.read().unwrap().is_book_position(&setup) {
+            return Ok(NoveltyReport {
+                last_book_ply: i,
+                novelty_ply: Some(i + 1),
+                opening,
+            });
+        }
+        if let Ok(named) = get_full_opening_from_setup(setup) {
+            opening = named;
+        }
+    }
+    Ok(NoveltyReport {
+        last_book_ply: moves.len(),
+        novelty_ply: None,
+        opening,
+    })
+}
+
+/// Replays the given SAN moves from the starting position and returns the deepest
+/// named opening along the way, with the moves that followed it
+#[tauri::command]
+pub fn nearest_named_opening(moves: Vec<String>) -> Result<NearestOpening, Error> {
+    let mut pos = Chess::default();
+    let mut opening = get_full_opening_from_setup(pos.clone().into_setup(EnPassantMode::Legal))?;
+    let mut opening_ply = 0;
+    for (i, mv) in moves.iter().enumerate() {
+        let m = parse_game_move(&pos, i + 1, mv)?;
+        pos.play_unchecked(&m);
+        if let Ok(named) = get_full_opening_from_setup(pos.clone().into_setup(EnPassantMode::Legal))
+        {
+            opening = named;
+            opening_ply = i + 1;
+        }
+    }
+    Ok(NearestOpening {
+        opening,
+        extra_moves: moves[opening_ply..].to_vec(),
+    })
+}
+
+/// Parses the SAN move played at a given ply of a game
+fn parse_game_move(pos: &Chess, ply: usize, mv: &str) -> Result<Move, Error> {
+    mv.parse::<SanPlus>()
+        .ok()
+        .and_then(|san| san.san.to_move(pos).ok())
+        .ok_or_else(|| Error::IllegalMoveAtPly(ply, mv.to_string()))
+}
+
+/// Parses the UCI move played at a given ply of a game
+fn parse_uci_move(pos: &Chess, ply: usize, mv: &str) -> Result<Move, Error> {
+    Uci::from_ascii(mv.as_bytes())
+        .ok()
+        .and_then(|uci| uci.to_move(pos).ok())
+        .ok_or_else(|| Error::IllegalMoveAtPly(ply, mv.to_string()))
+}
+
+/// Extracts the SAN moves of a PGN movetext, skipping move numbers, comments,
+/// variations, NAGs, move annotations and result tokens
+fn movetext_moves(movetext: &str) -> Vec<String> {
+    let mut moves = Vec::new();
+    let mut comment = false;
+    let mut variation_depth = 0usize;
+    let mut token = String::new();
+    for c in movetext.chars().chain(std::iter::once(' ')) {
+        match c {
+            _ if comment => comment = c != '}',
+            '{' => comment = true,
+            '(' => variation_depth += 1,
+            ')' => variation_depth = variation_depth.saturating_sub(1),
+            _ if variation_depth > 0 => {}
+            c if c.is_whitespace() => {
+                let san = token
+                    .rsplit('.')
+                    .next()
+                    .unwrap_or_default()
+                    .trim_end_matches(['!', '?']);
+                let is_result = matches!(san, "1-0" | "0-1" | "1/2-1/2" | "*");
+                if !san.is_empty() && !san.starts_with('$') && !is_result {
+                    moves.push(san.to_string());
+                }
+                token.clear();
+            }
+            c => token.push(c),
+        }
+    }
+    moves
+}
+
+/// Resets the halfmove clock and fullmove number to their initial values, and
+/// only keeps the en passant square if a pawn can capture there, like the book
+fn normalize_setup(setup: Setup) -> Setup {
+    let mut setup = match Chess::from_setup(setup.clone(), CastlingMode::Standard)
+        .or_else(PositionError::ignore_invalid_ep_square)
+    {
+        Ok(pos) => pos.into_setup(EnPassantMode::Legal),
+        Err(_) => setup,
+    };
+    setup.halfmoves = 0;
+    setup.fullmoves = NonZeroU32::MIN;
+    setup
+}
+
+/// Other names of opening families, normalized, with the name the table uses
+const OPENING_ALIASES: [(&str, &str); 12] = [
+    ("spanish", "Ruy Lopez"),
+    ("spanish game", "Ruy Lopez"),
+    ("spanish opening", "Ruy Lopez"),
+    ("petroff", "Russian Game"),
+    ("petrov", "Russian Game"),
+    ("petroff defense", "Russian Game"),
+    ("petrov defense", "Russian Game"),
+    ("petrov's defense", "Russian Game"),
+    ("giuoco piano", "Italian Game: Giuoco Piano"),
+    ("two knights defense", "Italian Game: Two Knights Defense"),
+    ("dragon", "Sicilian Defense: Dragon Variation"),
+    ("kid", "King's Indian Defense"),
+];
+
+/// Letter and description of each ECO volume
+const ECO_VOLUMES: [(&str, &str); 5] = [
+    ("A", "Flank openings"),
+    ("B", "Semi-open games other than the French Defense"),
+    ("C", "Open games and the French Defense"),
+    ("D", "Closed and semi-closed games"),
+    ("E", "Indian defenses"),
+];
+
+/// Themes inferred from the words of opening names
+const OPENING_TAGS: [&str; 5] = ["attack", "countergambit", "defense", "gambit", "system"];
+
+const DEFAULT_SEARCH_LIMIT: usize = 15;
+const MAX_SEARCH_LIMIT: usize = 200;
+/// Characters of a query kept for scoring, well past the longest opening name,
+/// so that a pasted blob doesn't slow down the comparison with every name
+const MAX_QUERY_CHARS: usize = 128;
+/// Score below which a name isn't considered to match the query at all
+const DEFAULT_MIN_SCORE: f64 = 0.5;
+/// Largest bonus popularity adds to a search score, enough to reorder near-equal
+/// matches but not to lift a common opening over a clearly better match
+const POPULARITY_WEIGHT: f64 = 0.02;
+/// Number of games at which an opening gets half of the popularity bonus
+const POPULARITY_SCALE: f64 = 1000.0;
+/// Score above which the closest name is suggested for a name that doesn't exist
+const DID_YOU_MEAN_MIN_SCORE: f64 = 0.75;
+/// Weight of the similarity of a multi-word query to a name once the words of both are
+/// sorted, slightly less than comparing them in order so that exact wordings rank first
+const WORD_ORDER_WEIGHT: f64 = 0.9;
+/// How many names a search scores between checks for a newer search
+const CANCEL_CHECK_INTERVAL: usize = 256;
+/// Number of callers whose latest search generation is remembered, past which the
+/// caller that searched least recently is forgotten
+const MAX_SEARCH_CALLERS: usize = 64;
+
+/// Next generation handed out by `next_search_generation`, which only goes up
+/// for the whole process
+static NEXT_SEARCH_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+/// Search of a caller numbered with a generation from `next_search_generation`
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchGeneration {
+    caller: String,
+    generation: u64,
+}
+
+/// Returns a generation higher than all the previous ones, for numbering a search
+#[tauri::command]
+pub fn next_search_generation() -> u64 {
+    NEXT_SEARCH_GENERATION.fetch_add(1, atomic::Ordering::Relaxed)
+}
+
+/// Returns the openings whose name best matches the query, which may be none.
+/// Names scoring below `min_score` are dropped before keeping the best `limit`.
+/// Queries that look like an ECO code, like "B90" or "B9", list the openings
+/// with a matching code first.
+/// The extra positions, like "Starting Position", are left out unless `include_extra` is set.
+/// Callers that number their searches with `next_search_generation` get
+/// `SearchCancelled` as soon as one of their searches with a higher generation starts.
+/// Names are compared with jaro-winkler unless another `similarity` is picked.
+/// Only the first 128 characters of the query are used.
+#[tauri::command]
+pub async fn search_opening_name(
+    query: String,
+    limit: Option<usize>,
+    min_score: Option<f64>,
+    generation: Option<SearchGeneration>,
+    include_extra: Option<bool>,
+    similarity: Option<SimilarityMode>,
+) -> Result<Vec<Opening>, Error> {
+    let matches = search_scored(
+        &query,
+        limit,
+        min_score,
+        generation,
+        include_extra,
+        similarity,
+        &[],
+    )?;
+    Ok(matches.into_iter().map(|(opening, _)| opening).collect())
+}
+
+/// Like `search_opening_name`, but leaves out the openings with one of the
+/// excluded names while still returning up to `limit` other openings
+#[tauri::command]
+pub async fn search_opening_name_excluding(
+    query: String,
+    exclude_names: Vec<String>,
+    limit: Option<usize>,
+) -> Result<Vec<Opening>, Error> {
+    let matches = search_scored(&query, limit, None, None, None, None, &exclude_names)?;
+    Ok(matches.into_iter().map(|(opening, _)| opening).collect())
+}
+
+/// Like `search_opening_name`, but pairs each opening with its score between 0
+/// and 1. Openings found by ECO code score 1.
+#[tauri::command]
+pub async fn search_opening_name_scored(
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<(Opening, f64)>, Error> {
+    search_scored(&query, limit, None, None, None, None, &[])
+}
+
+fn search_scored(
+    query: &str,
+    limit: Option<usize>,
+    min_score: Option<f64>,
+    generation: Option<SearchGeneration>,
+    include_extra: Option<bool>,
+    similarity: Option<SimilarityMode>,
+    excluded: &[String],
+) -> Result<Vec<(Opening, f64)>, Error> {
+    let limit = search_limit(limit);
+    let similarity = similarity.unwrap_or_default();
+    let min_score = min_score.unwrap_or(DEFAULT_MIN_SCORE);
+    let include_extra = include_extra.unwrap_or(false);
+    if let Some(search) = &generation {
+        track_search(&mut SEARCH_GENERATIONS.lock().unwrap(), search);
+    }
+    let Some(query) = prepare_query(query) else {
+        return Ok(Vec::new());
+    };
+    let superseded = || {
+        generation.as_ref().is_some_and(|search| {
+            SEARCH_GENERATIONS
+                .lock()
+                .unwrap()
+                .get(&search.caller)
+                .is_some_and(|&newest| newest > search.generation)
+        })
+    };
+    // fetch enough matches to still have `limit` once the extra positions are gone
+    let fetched = if include_extra {
+        limit
+    } else {
+        limit + EXTRA_OPENINGS.len()
+    };
+    let mut matches = USER_OPENINGS
+        .read()
+        .unwrap()
+        .find_best_matches_until(query, fetched, min_score, similarity, excluded, |_| {
+            superseded()
+        })
+        .ok_or(Error::SearchCancelled)?;
+    if !include_extra {
+        matches.retain(|(o, _)| o.eco != "Extra");
+    }
+    matches.truncate(limit);
+    Ok(eco_first(&eco_matches(query), matches, excluded, limit))
+}
+
+/// Records the generation of a search as the latest of its caller unless a newer
+/// one is known, forgetting the least recent caller once `MAX_SEARCH_CALLERS` are
+/// tracked, since every mount of a search box picks a new caller
+fn track_search(latest: &mut HashMap<String, u64>, search: &SearchGeneration) {
+    if !latest.contains_key(&search.caller) && latest.len() >= MAX_SEARCH_CALLERS {
+        let oldest = latest
+            .iter()
+            .min_by_key(|(_, generation)| **generation)
+            .map(|(caller, _)| caller.clone());
+        if let Some(oldest) = oldest {
+            latest.remove(&oldest);
+        }
+    }
+    let newest = latest.entry(search.caller.clone()).or_default();
+    *newest = (*newest).max(search.generation);
+}
+
+/// Trims a search query and cuts it to `MAX_QUERY_CHARS` characters, or returns `None`
+/// for a blank one, which would only list arbitrary openings with a score close to 0
+fn prepare_query(query: &str) -> Option<&str> {
+    let query = query.trim();
+    if query.is_empty() {
+        return None;
+    }
+    Some(char_prefix(query, MAX_QUERY_CHARS))
+}
+
+/// Openings whose ECO code starts with the query, when it looks like one
+fn eco_matches(query: &str) -> Vec<Opening> {
+    if !is_eco_query(query) {
+        return Vec::new();
+    }
+    let code = query.to_uppercase();
+    collect_by_eco(|eco| eco.starts_with(&code)).unwrap_or_default()
+}
+
+/// Puts the openings of the queried ECO code first, with a perfect score, and keeps
+/// `limit` matches with distinct names that aren't excluded
+fn eco_first(
+    by_eco: &[Opening],
+    matches: Vec<(Opening, f64)>,
+    excluded: &[String],
+    limit: usize,
+) -> Vec<(Opening, f64)> {
+    if by_eco.is_empty() {
+        return matches;
+    }
+    let mut names: HashSet<String> = excluded.iter().cloned().collect();
+    by_eco
+        .iter()
+        .map(|opening| (opening.clone(), 1.0))
+        .chain(matches)
+        .filter(|(o, _)| names.insert(o.name.clone()))
+        .take(limit)
+        .collect()
+}
+
+/// Searches openings like `search_opening_name_scored`, but emits the best
+/// matches found so far as `opening_search_results` events while scanning,
+/// the last one being marked as finished
+#[tauri::command]
+pub async fn search_opening_name_streamed(
+    query: String,
+    limit: Option<usize>,
+    id: u64,
+    app: tauri::AppHandle,
+) -> Result<(), Error> {
+    stream_search(&query, limit, |openings, finished| {
+        app.emit_all(
+            "opening_search_results",
+            OpeningSearchPayload {
+                id,
+                openings,
+                finished,
+            },
+        )
+    })?;
+    Ok(())
+}
+
+/// Runs a fuzzy name search, calling `emit` with the best matches whenever they
+/// changed at a checkpoint of the scan, and once more with the final matches.
+/// The query is handled like in `search_opening_name`, so a blank one only emits
+/// an empty final result and an ECO code lists its openings first.
+fn stream_search<E>(
+    query: &str,
+    limit: Option<usize>,
+    mut emit: impl FnMut(Vec<(Opening, f64)>, bool) -> Result<(), E>,
+) -> Result<(), E> {
+    let limit = search_limit(limit);
+    let Some(query) = prepare_query(query) else {
+        return emit(Vec::new(), true);
+    };
+    let by_eco = eco_matches(query);
+    let without_extra = |mut matches: Vec<(Opening, f64)>| {
+        matches.retain(|(o, _)| o.eco != "Extra");
+        matches.truncate(limit);
+        eco_first(&by_eco, matches, &[], limit)
+    };
+
+    let mut emitted: Vec<usize> = Vec::new();
+    let mut error = None;
+    let matches = USER_OPENINGS.read().unwrap().find_best_matches_until(
+        query,
+        limit + EXTRA_OPENINGS.len(),
+        DEFAULT_MIN_SCORE,
+        SimilarityMode::default(),
+        &[],
+        |best| {
+            let mut indices: Vec<usize> = best.iter().map(|c| c.index).collect();
+            indices.sort_unstable();
+            if indices == emitted {
+                return false;
+            }
+            emitted = indices;
+            match emit(without_extra(scored_matches(best.clone())), false) {
+                Ok(()) => false,
+                Err(e) => {
+                    error = Some(e);
+                    true
+                }
+            }
+        },
+    );
+    if let Some(e) = error {
+        return Err(e);
+    }
+    emit(without_extra(matches.unwrap_or_default()), true)
+}
+
+/// Whether a query is a letter of the ECO volumes followed by one or two digits
+fn is_eco_query(query: &str) -> bool {
+    let query = query.trim().as_bytes();
+    matches!(query, [volume, digits @ ..]
+        if (b'A'..=b'E').contains(&volume.to_ascii_uppercase())
+            && (1..=2).contains(&digits.len())
+            && digits.iter().all(u8::is_ascii_digit))
+}
+
+/// The first `max_chars` characters of a string, cut on a character boundary
+fn char_prefix(s: &str, max_chars: usize) -> &str {
+    match s.char_indices().nth(max_chars) {
+        Some((end, _)) => &s[..end],
+        None => s,
+    }
+}
+
+fn search_limit(limit: Option<usize>) -> usize {
+    limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT)
+}
+
+/// Returns the openings whose name starts with the prefix, ignoring case and accents,
+/// shortest names first
+#[tauri::command]
+pub fn autocomplete_opening(prefix: String, limit: usize) -> Result<Vec<Opening>, Error> {
+    let prefix = normalize_name(prefix.trim());
+    if prefix.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut matches: Vec<&Opening> = CANONICAL_INDICES
+        .iter()
+        .filter(|&&i| SEARCH_NAMES[i].starts_with(&prefix))
+        .map(|&i| OPENINGS[i])
+        .collect();
+    if matches.is_empty() {
+        return Err(Error::NoMatchFound);
+    }
+
+    matches.sort_by(|a, b| {
+        a.name
+            .len()
+            .cmp(&b.name.len())
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(matches
+        .into_iter()
+        .take(limit.clamp(1, MAX_SEARCH_LIMIT))
+        .cloned()
+        .collect())
+}
+
+/// Splits an opening name into its family, variation and sub-variations
+#[tauri::command]
+pub fn split_opening_name(name: String) -> Result<NameParts, Error> {
+    let (family, rest) = match name.split_once(':') {
+        Some((family, rest)) => (family, Some(rest)),
+        None => (name.as_str(), None),
+    };
+    let family = family.trim();
+    if family.is_empty() {
+        return Err(Error::NoOpeningFound);
+    }
+
+    let mut parts = rest
+        .into_iter()
+        .flat_map(|rest| rest.split(','))
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(str::to_string);
+    Ok(NameParts {
+        family: family.to_string(),
+        variation: parts.next(),
+        sub_variations: parts.collect(),
+    })
+}
+
+/// Lists the openings in alphabetical order, one per name, optionally only those
+/// whose name starts with a letter, ignoring case and accents
+#[tauri::command]
+pub fn list_opening_names(
+    starts_with: Option<char>,
+    limit: usize,
+    offset: usize,
+) -> Result<Vec<Opening>, Error> {
+    let prefix = starts_with
+        .map(|c| normalize_name(&c.to_string()))
+        .unwrap_or_default();
+    let mut names: Vec<(&str, usize)> = OPENINGS_BY_NAME
+        .values()
+        .map(|&i| (SEARCH_NAMES[i].as_str(), i))
+        .filter(|(name, _)| name.starts_with(&prefix))
+        .collect();
+    names.sort_by(|(a, i), (b, j)| {
+        a.cmp(b)
+            .then_with(|| OPENINGS[*i].name.cmp(&OPENINGS[*j].name))
+    });
+    Ok(names
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|(_, i)| OPENINGS[i].clone())
+        .collect())
+}
+
+/// Searches openings like `search_opening_name` and groups the matches by ECO
+/// volume, keeping them sorted by score within each group
+#[tauri::command]
+pub async fn search_opening_grouped(
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<(String, Vec<Opening>)>, Error> {
+    let mut groups: BTreeMap<String, Vec<Opening>> = BTreeMap::new();
+    for opening in find_best_matches(&query, search_limit(limit), DEFAULT_MIN_SCORE) {
+        groups
+            .entry(eco_volume(&opening.eco).to_string())
+            .or_default()
+            .push(opening);
+    }
+    Ok(groups.into_iter().collect())
+}
+
+/// Searches the variations of an opening family, like "Sicilian Defense", scoring
+/// only the part of their names after the family
+#[tauri::command]
+pub async fn search_opening_in_family(
+    family: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<Opening>, Error> {
+    let family = normalize_name(family.trim());
+    let query = normalize_name(query.trim());
+    let query_tokens: Vec<&str> = name_tokens(&query).collect();
+    let sorted_query = sorted_words(&query);
+
+    let mut matches: Vec<(&Opening, f64)> = CANONICAL_INDICES
+        .iter()
+        .map(|&i| (OPENINGS[i], &SEARCH_NAMES[i]))
+        .filter_map(|(opening, name)| {
+            let (name_family, variation) = name.split_once(':').unwrap_or((name.as_str(), ""));
+            if name_family.trim() != family {
+                return None;
+            }
+            let variation = variation.trim();
+            let score = name_score(
+                SimilarityMode::default(),
+                &query,
+                &query_tokens,
+                &sorted_query,
+                variation,
+                &sorted_words(variation),
+            );
+            Some((opening, score))
+        })
+        .collect();
+    if matches.is_empty() {
+        return Err(Error::NoOpeningFound);
+    }
+
+    matches.sort_by(|(a, x), (b, y)| y.total_cmp(x).then_with(|| a.name.cmp(&b.name)));
+    Ok(matches
+        .into_iter()
+        .filter(|(_, score)| *score >= DEFAULT_MIN_SCORE)
+        .take(search_limit(limit))
+        .map(|(opening, _)| opening.clone())
+        .collect())
+}
+
+/// Checks the loaded openings for duplicates, ambiguous names and lines that
+/// don't lead to their position, for contributors updating the data
+#[tauri::command]
+pub fn verify_opening_data() -> Result<DataReport, Error> {
+    let mut report = DataReport::default();
+    let mut rows: HashMap<(&str, &str, Option<&str>), usize> = HashMap::new();
+    let mut positions_by_name: BTreeMap<&str, HashSet<Setup>> = BTreeMap::new();
+    let mut names_by_position: HashMap<Setup, HashSet<&str>> = HashMap::new();
+
+    for opening in OPENINGS.iter() {
+        *rows
+            .entry((opening.eco(), opening.name(), opening.pgn()))
+            .or_default() += 1;
+        let setup = normalize_setup(opening.setup.clone());
+        positions_by_name
+            .entry(opening.name())
+            .or_default()
+            .insert(setup.clone());
+        names_by_position
+            .entry(setup.clone())
+            .or_default()
+            .insert(opening.name());
+
+        if let Some(pgn) = opening.pgn() {
+            if setup_from_pgn(pgn).ok().map(normalize_setup) != Some(setup) {
+                report.unreplayable.push(opening.name().to_string());
+            }
+        }
+    }
+
+    report.duplicate_entries = rows
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|((eco, name, pgn), _)| {
+            (
+                eco.to_string(),
+                name.to_string(),
+                pgn.unwrap_or_default().to_string(),
+            )
+        })
+        .collect();
+    report.duplicate_entries.sort();
+    report.names_with_multiple_positions = positions_by_name
+        .into_iter()
+        .filter(|(_, setups)| setups.len() > 1)
+        .map(|(name, _)| name.to_string())
+        .collect();
+    report.positions_with_multiple_names = names_by_position
+        .into_iter()
+        .filter(|(_, names)| names.len() > 1)
+        .map(|(setup, _)| Fen::from_setup(setup).to_string())
+        .collect();
+    report.positions_with_multiple_names.sort();
+    Ok(report)
+}
+
+/// Returns how many openings there are in each ECO volume, with the extra
+/// positions counted under "Extra"
+#[tauri::command]
+pub fn opening_counts_by_volume() -> Result<Vec<(String, usize)>, Error> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for opening in OPENINGS.iter() {
+        *counts.entry(eco_volume(&opening.eco)).or_default() += 1;
+    }
+    Ok(counts
+        .into_iter()
+        .map(|(volume, count)| (volume.to_string(), count))
+        .collect())
+}
+
+/// Describes the ECO volumes, with the number of openings of each, for navigating them
+#[tauri::command]
+pub fn eco_volumes() -> Vec<EcoVolume> {
+    ECO_VOLUMES
+        .iter()
+        .map(|(volume, description)| EcoVolume {
+            volume: volume.to_string(),
+            description: description.to_string(),
+            count: OPENINGS
+                .iter()
+                .filter(|o| eco_volume(&o.eco) == *volume)
+                .count(),
+        })
+        .collect()
+}
+
+/// Writes every opening of the ECO volumes to an `Openings` table of a SQLite
+/// database, replacing the table if it exists. Returns the number of rows written.
+#[tauri::command]
+pub fn export_openings_sqlite(path: PathBuf) -> Result<usize, Error> {
+    let mut db = SqliteConnection::establish(&path.to_string_lossy())?;
+    db.transaction::<_, Error, _>(|db| {
+        db.batch_execute(
+            "DROP TABLE IF EXISTS Openings;
+            CREATE TABLE Openings (eco TEXT, name TEXT, fen TEXT, pgn TEXT);",
+        )?;
+
+        let mut count = 0;
+        for opening in OPENINGS.iter().filter(|o| o.eco != "Extra") {
+            count += sql_query("INSERT INTO Openings (eco, name, fen, pgn) VALUES (?, ?, ?, ?)")
+                .bind::<Text, _>(opening.eco())
+                .bind::<Text, _>(opening.name())
+                .bind::<Text, _>(opening.fen())
+                .bind::<Nullable<Text>, _>(opening.pgn())
+                .execute(db)?;
+        }
+        Ok(count)
+    })
+}
+
+/// Returns the volume letter of an ECO code, or "Extra" for the extra positions
+fn eco_volume(eco: &str) -> &str {
+    if eco == "Extra" {
+        eco
+    } else {
+        eco.get(..1).unwrap_or(eco)
+    }
+}
+
+/// Returns the openings with a theme, like "gambit" or "defense"
+#[tauri::command]
+pub fn search_opening_by_tag(tag: String) -> Result<Vec<Opening>, Error> {
+    let tag = normalize_name(tag.trim());
+    Ok(OPENINGS
+        .iter()
+        .copied()
+        .filter(|o| o.tags.contains(&tag.as_str()))
+        .cloned()
+        .collect())
+}
+
+/// Words of a name that make it one of the `OPENING_TAGS`
+fn name_tags(name: &str) -> Vec<&'static str> {
+    let name = normalize_name(name);
+    OPENING_TAGS
+        .into_iter()
+        .filter(|tag| name_tokens(&name).any(|token| token == *tag))
+        .collect()
+}
+
+#[tauri::command]
+pub fn search_opening_by_eco(code: String) -> Result<Vec<Opening>, Error> {
+    collect_by_eco(|eco| eco == code)
+}
+
+/// Returns every opening with an ECO code between `from` and `to`, inclusive
+#[tauri::command]
+pub fn search_opening_by_eco_range(from: String, to: String) -> Result<Vec<Opening>, Error> {
+    collect_by_eco(|eco| eco != "Extra" && from.as_str() <= eco && eco <= to.as_str())
+}
+
+/// Collects the openings whose ECO code satisfies the predicate, sorted by ECO and name
+fn collect_by_eco(predicate: impl Fn(&str) -> bool) -> Result<Vec<Opening>, Error> {
+    let mut openings: Vec<Opening> = OPENINGS
+        .iter()
+        .copied()
+        .filter(|o| predicate(&o.eco))
+        .cloned()
+        .collect();
+    if openings.is_empty() {
+        return Err(Error::NoMatchFound);
+    }
+    openings.sort_by(|a, b| a.eco.cmp(&b.eco).then_with(|| a.name.cmp(&b.name)));
+    Ok(openings)
+}
+
+/// Search candidate, ordered so that the worst match is the greatest
+/// and therefore sits at the top of a `BinaryHeap`. Ties are broken by name.
+#[derive(Clone)]
+struct Candidate<'a> {
+    score: f64,
+    index: usize,
+    opening: &'a Opening,
+}
+
+impl PartialEq for Candidate<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate<'_> {}
+
+impl Ord for Candidate<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .score
+            .total_cmp(&self.score)
+            .then_with(|| self.opening.name.cmp(&other.opening.name))
+            .then(self.index.cmp(&other.index))
+    }
+}
+
+impl PartialOrd for Candidate<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn popularity_bonus(popularity: u32) -> f64 {
+    let popularity = f64::from(popularity);
+    POPULARITY_WEIGHT * popularity / (popularity + POPULARITY_SCALE)
+}
+
+/// Sorts the candidates from best to worst, with their scores capped to 1
+fn scored_matches(candidates: BinaryHeap<Candidate>) -> Vec<(Opening, f64)> {
+    candidates
+        .into_sorted_vec()
+        .into_iter()
+        .map(|c| (c.opening.clone(), c.score.min(1.0)))
+        .collect()
+}
+
+fn find_best_matches(query: &str, limit: usize, min_score: f64) -> Vec<Opening> {
+    USER_OPENINGS
+        .read()
+        .unwrap()
+        .find_best_matches(query, limit, min_score)
+}
+
+/// Format of a file of user openings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UserFormat {
+    /// Tab-separated `eco/name/pgn` table
+    Tsv,
+    /// One JSON object per line, like the lichess opening exports
+    Ndjson,
+}
+
+/// Line of an NDJSON file of openings, whose moves are either SAN or UCI
+#[derive(Deserialize)]
+struct NdjsonOpening {
+    eco: String,
+    name: String,
+    #[serde(default)]
+    pgn: Option<String>,
+    #[serde(default)]
+    uci: Option<String>,
+}
+
+/// Number of viewed openings remembered for the recent list
+const RECENT_OPENINGS_CAPACITY: usize = 50;
+
+/// Marks the named opening as the one viewed last
+#[tauri::command]
+pub fn record_opening_view(name: String) -> Result<(), Error> {
+    let opening = find_by_name(&name)?;
+    RECENT_OPENINGS.lock().unwrap().record(&opening.name);
+    Ok(())
+}
+
+/// Returns up to `limit` of the openings viewed last, most recent first
+#[tauri::command]
+pub fn get_recent_openings(limit: usize) -> Result<Vec<Opening>, Error> {
+    Ok(RECENT_OPENINGS.lock().unwrap().openings(limit))
+}
+
+/// Names of the viewed openings, most recent first, each at most once
+#[derive(Debug, Default)]
+struct RecentOpenings {
+    names: VecDeque<String>,
+}
+
+impl RecentOpenings {
+    fn record(&mut self, name: &str) {
+        self.names.retain(|n| n != name);
+        self.names.push_front(name.to_string());
+        self.names.truncate(RECENT_OPENINGS_CAPACITY);
+    }
+
+    /// Returns up to `limit` of the remembered openings, skipping the names that
+    /// don't resolve anymore, like those of user openings that were reloaded away
+    fn openings(&self, limit: usize) -> Vec<Opening> {
+        self.names
+            .iter()
+            .filter_map(|name| find_by_name(name).ok())
+            .take(limit)
+            .collect()
+    }
+}
+
+/// Openings loaded by the user, which are consulted before the bundled ones
+#[derive(Debug, Default)]
+struct UserOpenings {
+    openings: Vec<Opening>,
+    /// Indices of the entries with each normalized position, in load order
+    by_setup: HashMap<Setup, Vec<usize>>,
+    /// Index of the last loaded entry with each canonical name
+    by_name: HashMap<String, usize>,
+    /// Normalized positions along the lines of the entries, like `BOOK_POSITIONS`
+    book_positions: HashSet<Setup>,
+    paths: Vec<(PathBuf, UserFormat)>,
+}
+
+impl UserOpenings {
+    /// Builds a fresh overlay from the given files, in order
+    fn from_paths(paths: Vec<(PathBuf, UserFormat)>) -> Result<Self, Error> {
+        let mut user = UserOpenings::default();
+        for (path, format) in paths {
+            user.load_path(path, format)?;
+        }
+        Ok(user)
+    }
+
+    /// Loads a file and remembers its path so that it is picked up again on reload
+    fn load_path(&mut self, path: PathBuf, format: UserFormat) -> Result<usize, Error> {
+        let file = File::open(&path)?;
+        let count = match format {
+            UserFormat::Tsv => self.load(file)?,
+            UserFormat::Ndjson => self.load_ndjson(BufReader::new(file))?,
+        };
+        if !self.paths.iter().any(|(p, _)| *p == path) {
+            self.paths.push((path, format));
+        }
+        Ok(count)
+    }
+
+    /// Adds the openings of an NDJSON file and returns how many were loaded.
+    /// Like with tables, a line with an illegal move fails the whole file.
+    fn load_ndjson<R: BufRead>(&mut self, reader: R) -> Result<usize, Error> {
+        let mut openings = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: NdjsonOpening = serde_json::from_str(&line)?;
+            let pgn = match (record.pgn, record.uci) {
+                (Some(pgn), _) => pgn,
+                (None, Some(uci)) => pgn_from_uci(&uci)?,
+                (None, None) => return Err(Error::NoMovesFound),
+            };
+            let setup = setup_from_pgn(&pgn)?;
+            openings.push(Opening::new(record.eco, record.name, setup, Some(pgn), 0));
+        }
+        Ok(self.add(openings))
+    }
+
+    /// Adds the openings of a tab-separated table and returns how many were loaded.
+    /// Unlike with the bundled data, a line with an illegal move fails the whole table.
+    fn load<R: Read>(&mut self, reader: R) -> Result<usize, Error> {
+        let (records, _) = read_records(reader);
+        let openings = records
+            .into_iter()
+            .map(|record| {
+                let setup = setup_from_pgn(&record.pgn)?;
+                Ok(record.into_opening(setup))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(self.add(openings))
+    }
+
+    /// Appends parsed openings, indexing their normalized positions, and returns their number
+    fn add(&mut self, openings: Vec<Opening>) -> usize {
+        let count = openings.len();
+        for opening in openings {
+            let i = self.openings.len();
+            self.by_setup
+                .entry(normalize_setup(opening.setup.clone()))
+                .or_default()
+                .push(i);
+            self.by_name.insert(canonical_name(&opening.name), i);
+            if let Some(pgn) = opening.pgn() {
+                let _ = replay_pgn(pgn, |pos| {
+                    self.book_positions.insert(normalize_setup(
+                        pos.clone().into_setup(EnPassantMode::Legal),
+                    ));
+                });
+            }
+            self.openings.push(opening);
+        }
+        count
+    }
+
+    /// Whether a normalized position is on the line of a user or bundled opening
+    fn is_book_position(&self, setup: &Setup) -> bool {
+        self.book_positions.contains(setup) || BOOK_POSITIONS.contains(setup)
+    }
+
+    /// Returns the opening with this normalized position, preferring the most recently
+    /// loaded user entry over the bundled table
+    fn find_by_setup(&self, setup: &Setup) -> Option<&Opening> {
+        self.by_setup
+            .get(setup)
+            .and_then(|indices| indices.last())
+            .map(|&i| &self.openings[i])
+            // the extra positions are already normalized
+            .or_else(|| EXTRA_OPENINGS.iter().find(|o| o.setup == *setup))
+            .or_else(|| VOLUMES.find_by_setup(setup))
+    }
+
+    /// Returns every opening with this normalized position, the user entries first,
+    /// most recently loaded first, and then the bundled ones in table order
+    fn find_all_by_setup(&self, setup: &Setup) -> Vec<&Opening> {
+        let user = self
+            .by_setup
+            .get(setup)
+            .into_iter()
+            .flat_map(|indices| indices.iter().rev().map(|&i| &self.openings[i]));
+        let bundled = openings_by_setup(setup).into_iter().map(|i| OPENINGS[i]);
+        user.chain(bundled).collect()
+    }
+
+    /// Returns the opening with the shortest line among those with this piece placement
+    /// and side to move, looking at the user entries before the bundled ones
+    fn find_by_board(&self, board: &Board, turn: Color) -> Option<&Opening> {
+        let user = self.openings.iter().rev();
+        let bundled = OPENINGS.iter().copied();
+        shortest_line(
+            user.chain(bundled)
+                .filter(|o| o.setup.board == *board && o.setup.turn == turn),
+        )
+    }
+
+    /// Returns the opening with this name, preferring the most recently loaded user
+    /// entry over the canonical one of the bundled table
+    fn find_by_name(&self, name: &str) -> Option<&Opening> {
+        let name = canonical_name(name);
+        self.by_name
+            .get(&name)
+            .map(|&i| &self.openings[i])
+            .or_else(|| OPENINGS_BY_NAME.get(&name).map(|&i| OPENINGS[i]))
+    }
+
+    /// Returns up to `limit` openings with distinct names scoring at least `min_score`,
+    /// sorted by similarity to the query
+    fn find_best_matches(&self, query: &str, limit: usize, min_score: f64) -> Vec<Opening> {
+        self.find_best_matches_until(
+            query,
+            limit,
+            min_score,
+            SimilarityMode::default(),
+            &[],
+            |_| false,
+        )
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(opening, _)| opening)
+        .collect()
+    }
+
+    /// Like `find_best_matches`, but keeps the score of each match and skips the
+    /// excluded names, comparing names with the given metric. Every few hundred
+    /// names, `checkpoint` is shown the best matches so far, and the search gives
+    /// up, returning `None`, if it returns true.
+    fn find_best_matches_until(
+        &self,
+        query: &str,
+        limit: usize,
+        min_score: f64,
+        mode: SimilarityMode,
+        excluded: &[String],
+        mut checkpoint: impl FnMut(&BinaryHeap<Candidate>) -> bool,
+    ) -> Option<Vec<(Opening, f64)>> {
+        let query = normalize_name(query);
+        let query_tokens: Vec<&str> = name_tokens(&query).collect();
+        let sorted_query = sorted_words(&query);
+        let user_names: Vec<(String, String)> = self
+            .openings
+            .iter()
+            .map(|o| {
+                let name = normalize_name(&o.name);
+                let sorted = sorted_words(&name);
+                (name, sorted)
+            })
+            .collect();
+        let candidates = self
+            .openings
+            .iter()
+            .zip(user_names.iter().map(|(name, sorted)| (name, sorted)))
+            .chain(
+                CANONICAL_INDICES
+                    .iter()
+                    .map(|&i| (OPENINGS[i], (&SEARCH_NAMES[i], &SORTED_SEARCH_NAMES[i]))),
+            );
+
+        let mut best_matches: BinaryHeap<Candidate> = BinaryHeap::with_capacity(limit + 1);
+        // excluded names are skipped like the names already kept
+        let mut names: HashSet<&str> = excluded.iter().map(String::as_str).collect();
+
+        for (index, (opening, (name, sorted_name))) in candidates.enumerate() {
+            if index % CANCEL_CHECK_INTERVAL == 0 && checkpoint(&best_matches) {
+                return None;
+            }
+            if names.contains(opening.name.as_str()) {
+                continue;
+            }
+
+            let score = name_score(
+                mode,
+                &query,
+                &query_tokens,
+                &sorted_query,
+                name,
+                sorted_name,
+            );
+            if score < min_score {
+                continue;
+            }
+            let score = score + popularity_bonus(opening.popularity);
+            let candidate = Candidate {
+                score,
+                index,
+                opening,
+            };
+
+            if best_matches.len() < limit {
+                best_matches.push(candidate);
+                names.insert(&opening.name);
+            } else if best_matches.peek().is_some_and(|worst| candidate < *worst) {
+                if let Some(worst) = best_matches.pop() {
+                    names.remove(worst.opening.name.as_str());
+                }
+                best_matches.push(candidate);
+                names.insert(&opening.name);
+            }
+        }
+
+        Some(scored_matches(best_matches))
+    }
+}
+
+/// Held while files are read into the user openings, so that a reload can't drop
+/// a file loaded while it was reading the others
+static USER_OPENINGS_LOADING: Mutex<()> = Mutex::new(());
+
+/// Adds the openings of a tab-separated `eco/name/pgn` file on top of the bundled ones.
+/// Returns the number of openings loaded.
+#[tauri::command]
+pub fn load_user_openings(path: PathBuf) -> Result<usize, Error> {
+    let _loading = USER_OPENINGS_LOADING.lock().unwrap();
+    USER_OPENINGS
+        .write()
+        .unwrap()
+        .load_path(path, UserFormat::Tsv)
+}
+
+/// Adds the openings of a newline-delimited JSON file, with an `eco`, a `name`
+/// and either a `pgn` or a `uci` field on each line, like the lichess exports.
+/// Returns the number of openings loaded.
+#[tauri::command]
+pub fn load_openings_ndjson(path: String) -> Result<usize, Error> {
+    let _loading = USER_OPENINGS_LOADING.lock().unwrap();
+    USER_OPENINGS
+        .write()
+        .unwrap()
+        .load_path(PathBuf::from(path), UserFormat::Ndjson)
+}
+
+/// Writes space-separated UCI moves as a SAN movetext with move numbers
+fn pgn_from_uci(uci: &str) -> Result<String, Error> {
+    let mut pos = Chess::default();
+    let mut tokens = Vec::new();
+    for (i, mv) in uci.split_whitespace().enumerate() {
+        let m = parse_uci_move(&pos, i + 1, mv)?;
+        if i % 2 == 0 {
+            tokens.push(format!("{}.", i / 2 + 1));
+        }
+        tokens.push(San::from_move(&pos, &m).to_string());
+        pos.play_unchecked(&m);
+    }
+    Ok(tokens.join(" "))
+}
+
+/// Clears the user openings and reads every previously loaded file again.
+/// Returns the number of openings in the new overlay.
+#[tauri::command]
+pub fn reload_user_openings() -> Result<usize, Error> {
+    let _loading = USER_OPENINGS_LOADING.lock().unwrap();
+    let paths = USER_OPENINGS.read().unwrap().paths.clone();
+    // Read the files without holding the lock, so lookups keep using the old overlay meanwhile
+    let user = UserOpenings::from_paths(paths)?;
+    let count = user.openings.len();
+    *USER_OPENINGS.write().unwrap() = user;
+    Ok(count)
+}
+
+/// Lowercases a name and strips its diacritics, so that searches are
+/// case- and accent-insensitive
+fn normalize_name(name: &str) -> String {
+    name.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Splits a name into its words, dropping punctuation
+fn name_tokens(name: &str) -> impl Iterator<Item = &str> {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+}
+
+/// Words of a name in alphabetical order
+fn sorted_words(name: &str) -> String {
+    let mut words: Vec<&str> = name_tokens(name).collect();
+    words.sort_unstable();
+    words.join(" ")
+}
+
+/// Similarity of a normalized query to a normalized name. Queries of several words
+/// are also compared with the words of both sorted, like "defense sicilian" to
+/// "sicilian defense", since jaro-winkler alone is very sensitive to word order.
+fn name_score(
+    mode: SimilarityMode,
+    query: &str,
+    query_tokens: &[&str],
+    sorted_query: &str,
+    name: &str,
+    sorted_name: &str,
+) -> f64 {
+    let mut similarity = mode.similarity(query, name);
+    if query_tokens.len() > 1 {
+        similarity = similarity.max(WORD_ORDER_WEIGHT * mode.similarity(sorted_query, sorted_name));
+    }
+    0.6 * similarity + 0.4 * token_overlap(query_tokens, name)
+}
+
+/// Fraction of the query tokens that appear inside some token of the name
+fn token_overlap(query_tokens: &[&str], name: &str) -> f64 {
+    if query_tokens.is_empty() {
+        return 0.0;
+    }
+    let found = query_tokens
+        .iter()
+        .filter(|q| name_tokens(name).any(|t| t.contains(*q)))
+        .count();
+    found as f64 / query_tokens.len() as f64
+}
+
+/// Parses a tab-separated `eco/name/pgn` table, skipping malformed rows and
+/// lines with an illegal move. Returns the parsed openings along with the number
+/// of skipped rows.
+fn read_openings<R: Read>(reader: R) -> (Vec<Opening>, usize) {
+    let (records, mut skipped) = read_records(reader);
+    let mut openings = Vec::with_capacity(records.len());
+    for record in records {
+        match setup_from_pgn(&record.pgn) {
+            Ok(setup) => openings.push(record.into_opening(setup)),
+            Err(e) => {
+                warn!("Skipping opening {}: {}", record.name, e);
+                skipped += 1;
+            }
+        }
+    }
+    (openings, skipped)
+}
+
+/// Deserializes the rows of a tab-separated `eco/name/pgn` table, skipping
+/// malformed ones. Returns the records along with the number of skipped rows.
+fn read_records<R: Read>(reader: R) -> (Vec<OpeningRecord>, usize) {
+    let mut records = Vec::new();
+    let mut skipped = 0;
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .from_reader(reader);
+    for result in rdr.deserialize() {
+        match result {
+            Ok(record) => records.push(record),
+            Err(e) => {
+                warn!("Failed to deserialize opening: {}", e);
+                skipped += 1;
+            }
+        }
+    }
+
+    (records, skipped)
+}
+
+/// Inflates and decodes the prebuilt openings index, which only has to parse FENs
+/// instead of replaying the moves of every line
+fn read_openings_index(bytes: &[u8]) -> Result<Vec<Opening>, Error> {
+    let mut data = Vec::new();
+    GzDecoder::new(bytes).read_to_end(&mut data)?;
+    let (records, _): (Vec<IndexedOpening>, usize) =
+        bincode::decode_from_slice(&data, config::standard())?;
+    records
+        .into_iter()
+        .map(|record| {
+            let fen: Fen = record.fen.parse()?;
+            Ok(Opening::new(
+                record.eco,
+                record.name,
+                fen.into_setup(),
+                Some(record.pgn),
+                record.popularity,
+            ))
+        })
+        .collect()
+}
+
+/// Inflates a gzipped asset, logging a warning if it's corrupt
+fn decompress(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut data = Vec::new();
+    match GzDecoder::new(bytes).read_to_end(&mut data) {
+        Ok(_) => Some(data),
+        Err(e) => {
+            warn!("Failed to decompress openings: {}", e);
+            None
+        }
+    }
+}
+
+/// Parses a bundled TSV file, replaying the moves of every line
+fn read_tsv_volume(tsv: &[u8]) -> Vec<Opening> {
+    let Some(tsv) = decompress(tsv) else {
+        return Vec::new();
+    };
+    let (openings, skipped) = read_openings(tsv.as_slice());
+    if skipped > 0 {
+        warn!("Skipped {} malformed openings", skipped);
+    }
+    openings
+}
+
+/// Openings of one ECO volume
+#[derive(Debug)]
+struct Volume {
+    openings: Vec<Opening>,
+    /// Indices of the entries with each normalized position, in table order
+    by_setup: HashMap<Setup, Vec<usize>>,
+}
+
+impl Volume {
+    /// Reads a volume from the prebuilt index, parsing its TSV file if the index is unusable
+    fn load(volume: usize) -> Self {
+        info!(
+            "Loading openings volume {}...",
+            (b'A' + volume as u8) as char
+        );
+        let openings = match read_openings_index(INDEX_DATA[volume]) {
+            Ok(openings) => openings,
+            Err(e) => {
+                warn!(
+                    "Failed to read openings index, parsing TSV file instead: {}",
+                    e
+                );
+                read_tsv_volume(TSV_DATA[volume])
+            }
+        };
+        Volume::from_openings(openings)
+    }
+
+    /// Reads a volume from its TSV file in a directory
+    fn read_dir(dir: &Path, volume: usize) -> Result<Self, Error> {
+        let path = dir.join(format!("{}.tsv", (b'a' + volume as u8) as char));
+        info!("Loading openings volume from {}...", path.display());
+        let (openings, skipped) = read_openings(File::open(path)?);
+        if skipped > 0 {
+            warn!("Skipped {} malformed openings", skipped);
+        }
+        Ok(Volume::from_openings(openings))
+    }
+
+    fn from_openings(openings: Vec<Opening>) -> Self {
+        let mut by_setup: HashMap<Setup, Vec<usize>> = HashMap::with_capacity(openings.len());
+        for (i, opening) in openings.iter().enumerate() {
+            by_setup
+                .entry(normalize_setup(opening.setup.clone()))
+                .or_default()
+                .push(i);
+        }
+        Volume { openings, by_setup }
+    }
+}
+
+/// The five ECO volumes, each loaded the first time it's needed
+struct Volumes {
+    volumes: [OnceCell<Volume>; 5],
+    loads: AtomicUsize,
+    /// Directory overriding the bundled data, see `OPENINGS_DIR_VAR`
+    dir: OnceCell<Option<PathBuf>>,
+}
+
+impl Volumes {
+    const fn new() -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const UNLOADED: OnceCell<Volume> = OnceCell::new();
+        Volumes {
+            volumes: [UNLOADED; 5],
+            loads: AtomicUsize::new(0),
+            dir: OnceCell::new(),
+        }
+    }
+
+    fn get(&self, volume: usize) -> &Volume {
+        self.volumes[volume].get_or_init(|| {
+            self.loads.fetch_add(1, atomic::Ordering::Relaxed);
+            let Some(dir) = self.dir() else {
+                return Volume::load(volume);
+            };
+            Volume::read_dir(dir, volume).unwrap_or_else(|e| {
+                warn!(
+                    "Failed to read openings, using the bundled ones instead: {}",
+                    e
+                );
+                Volume::load(volume)
+            })
+        })
+    }
+
+    fn dir(&self) -> Option<&Path> {
+        self.dir
+            .get_or_init(|| env::var_os(OPENINGS_DIR_VAR).map(PathBuf::from))
+            .as_deref()
+    }
+
+    /// Reads every volume from a directory instead of the bundled data, which is
+    /// only possible before any of them was loaded. Returns the number of openings read.
+    /// A volume that can't be read fails the call, and is then read like with
+    /// `OPENINGS_DIR_VAR` when it's needed, falling back to the bundled one.
+    fn load_dir(&self, dir: PathBuf) -> Result<usize, Error> {
+        // loading a volume picks the directory first, so this fails once any volume
+        // started loading, and volumes loading from now on read the new directory
+        self.dir
+            .set(Some(dir.clone()))
+            .map_err(|_| Error::OpeningsAlreadyLoaded)?;
+        let mut count = 0;
+        for (i, cell) in self.volumes.iter().enumerate() {
+            let volume = Volume::read_dir(&dir, i)?;
+            count += volume.openings.len();
+            // a lookup may have read it from the same directory in the meantime
+            if cell.set(volume).is_ok() {
+                self.loads.fetch_add(1, atomic::Ordering::Relaxed);
+            }
+        }
+        Ok(count)
+    }
+
+    /// Returns the first opening with this normalized position, only loading
+    /// the volumes up to the one that has it
+    fn find_by_setup(&self, setup: &Setup) -> Option<&Opening> {
+        (0..self.volumes.len()).find_map(|i| {
+            let volume = self.get(i);
+            volume
+                .by_setup
+                .get(setup)
+                .map(|indices| &volume.openings[indices[0]])
+        })
+    }
+
+    /// Returns the indices into `OPENINGS` of every volume entry with this normalized
+    /// position, in table order
+    fn indices_by_setup(&self, setup: &Setup) -> Vec<usize> {
+        let mut indices = Vec::new();
+        let mut offset = EXTRA_OPENINGS.len();
+        for i in 0..self.volumes.len() {
+            let volume = self.get(i);
+            if let Some(found) = volume.by_setup.get(setup) {
+                indices.extend(found.iter().map(|&j| offset + j));
+            }
+            offset += volume.openings.len();
+        }
+        indices
+    }
+
+    /// Iterates over the openings of every volume, in table order
+    fn iter(&self) -> impl Iterator<Item = &Opening> {
+        (0..self.volumes.len()).flat_map(|i| self.get(i).openings.iter())
+    }
+}
+
+static VOLUMES: Volumes = Volumes::new();
+
+/// Replaces the bundled openings with the `a.tsv` to `e.tsv` files of a directory.
+/// Like setting `EN_CROISSANT_OPENINGS_DIR`, this must happen before the openings
+/// are first used. Returns the number of openings loaded.
+#[tauri::command]
+pub fn load_openings_from_dir(dir: String) -> Result<usize, Error> {
+    VOLUMES.load_dir(PathBuf::from(dir))
+}
+
+/// Reads the moves of a pgn from the starting position, skipping move numbers,
+/// comments, variations, NAGs and results like `movetext_moves` does.
+/// Moves are read as SAN, or else as long algebraic like `g1f3` or `Ng1-f3`.
+/// Other tokens are dropped with a warning. Fails on the first illegal move.
+fn pgn_moves(pgn: &str) -> Result<Vec<Move>, Error> {
+    let mut pos = Chess::default();
+    let mut moves = Vec::new();
+    for token in movetext_moves(pgn) {
+        let san = token.parse::<San>().ok();
+        let uci = long_algebraic(&token);
+        if san.is_none() && uci.is_none() {
+            warn!("Dropping token {:?} of opening line {:?}", token, pgn);
+            continue;
+        }
+        let ply = moves.len() + 1;
+        let m = san
+            .and_then(|san| san.to_move(&pos).ok())
+            .or_else(|| uci.and_then(|uci| uci.to_move(&pos).ok()))
+            .ok_or(Error::IllegalMoveAtPly(ply, token))?;
+        pos.play_unchecked(&m);
+        moves.push(m);
+    }
+    Ok(moves)
+}
+
+/// Plays the moves of a pgn, read by `pgn_moves`, from the starting position and
+/// calls `visit` with the position after each move
+fn replay_pgn(pgn: &str, mut visit: impl FnMut(&Chess)) -> Result<Chess, Error> {
+    let mut pos = Chess::default();
+    for m in pgn_moves(pgn)? {
+        pos.play_unchecked(&m);
+        visit(&pos);
+    }
+    Ok(pos)
+}
+
+/// Reads a move in long algebraic notation, like `g1f3`, `Ng1-f3` or `e7xd8=Q`
+fn long_algebraic(token: &str) -> Option<Uci> {
+    let token = token.trim_end_matches(['+', '#']);
+    let squares = token
+        .strip_prefix(['N', 'B', 'R', 'Q', 'K'])
+        .unwrap_or(token);
+    let uci: String = squares
+        .chars()
+        .filter(|c| !matches!(c, '-' | 'x' | '='))
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    Uci::from_ascii(uci.as_bytes()).ok()
+}
+
+fn setup_from_pgn(pgn: &str) -> Result<Setup, Error> {
+    replay_pgn(pgn, |_| {}).map(|pos| pos.into_setup(EnPassantMode::Legal))
+}
+
+lazy_static! {
+    /// Positions outside of the ECO volumes
+    static ref EXTRA_OPENINGS: Vec<Opening> = vec![
+        Opening::new(
+            "Extra".to_string(),
+            "Starting Position".to_string(),
+            Setup::default(),
+            None,
+            0,
+        ),
+        Opening::new(
+            "Extra".to_string(),
+            "Empty Board".to_string(),
+            Setup::empty(),
+            None,
+            0,
+        ),
+    ];
+
+    /// Every opening, starting with the extra positions and followed by the volumes in order.
+    /// The entries stay in their volume, this only points to them.
+    static ref OPENINGS: Vec<&'static Opening> = {
+        info!("Initializing openings table...");
+        EXTRA_OPENINGS.iter().chain(VOLUMES.iter()).collect()
+    };
+
+    /// First entry of `OPENINGS` with a given position hash
+    static ref OPENINGS_BY_HASH: HashMap<u64, usize> = {
+        let mut index = HashMap::with_capacity(OPENINGS.len());
+        for (i, opening) in OPENINGS.iter().enumerate() {
+            if let Some(hash) = position_hash(opening.setup.clone()) {
+                index.entry(hash).or_insert(i);
+            }
+        }
+        index
+    };
+
+    /// Canonical entry of each name in `OPENINGS`
+    static ref OPENINGS_BY_NAME: HashMap<String, usize> = index_names(&OPENINGS);
+
+    /// Indices into `OPENINGS` of the canonical entries, in table order. Name searches
+    /// only go through these, so that they agree with the lookups by name.
+    static ref CANONICAL_INDICES: Vec<usize> = {
+        let mut indices: Vec<usize> = OPENINGS_BY_NAME.values().copied().collect();
+        indices.sort_unstable();
+        indices
+    };
+
+    /// Normalized names of `OPENINGS`, used for fuzzy searching
+    static ref SEARCH_NAMES: Vec<String> =
+        OPENINGS.iter().map(|o| normalize_name(&o.name)).collect();
+
+    /// Words of each of the `SEARCH_NAMES` in alphabetical order
+    static ref SORTED_SEARCH_NAMES: Vec<String> =
+        SEARCH_NAMES.iter().map(|name| sorted_words(name)).collect();
+
+    /// Normalized positions along the line of every opening, named or not
+    static ref BOOK_POSITIONS: HashSet<Setup> = {
+        let mut positions = HashSet::new();
+        positions.insert(normalize_setup(Setup::default()));
+        for pgn in OPENINGS.iter().filter_map(|o| o.pgn.as_deref()) {
+            let _ = replay_pgn(pgn, |pos| {
+                positions.insert(normalize_setup(pos.clone().into_setup(EnPassantMode::Legal)));
+            });
+        }
+        positions
+    };
+
+    static ref USER_OPENINGS: RwLock<UserOpenings> = RwLock::new(UserOpenings::default());
+
+    static ref RECENT_OPENINGS: Mutex<RecentOpenings> = Mutex::new(RecentOpenings::default());
+
+    /// Highest generation passed to `search_opening_name` by each recent caller, so that
+    /// the scan of a superseded query can bail out instead of holding up the newer one
+    static ref SEARCH_GENERATIONS: Mutex<HashMap<String, u64>> = Mutex::new(HashMap::new());
+
+    /// Hash of the uncompressed TSV files, see `opening_dataset_version`
+    static ref DATASET_VERSION: String = {
+        let tables: Vec<Vec<u8>> = TSV_DATA
+            .iter()
+            .map(|tsv| decompress(tsv).unwrap_or_default())
+            .collect();
+        dataset_version(tables.iter().map(Vec::as_slice))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    #[test]
+    fn test_preload_openings() {
+        let count = tauri::async_runtime::block_on(preload_openings()).unwrap();
+        assert!(count > EXTRA_OPENINGS.len());
+        assert_eq!(count, OPENINGS.len());
+
+        // every volume was read once, and the lookups don't read them again
+        let loads = || VOLUMES.loads.load(atomic::Ordering::Relaxed);
+        assert_eq!(loads(), VOLUMES.volumes.len());
+        let opening = get_opening_from_fen(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3",
+            None,
+        );
+        assert_eq!(opening.unwrap(), "Italian Game");
+        assert_eq!(find_by_name("Italian Game").unwrap().eco, "C50");
+        assert_eq!(loads(), VOLUMES.volumes.len());
+    }
+
+    #[test]
+    fn test_relaxed_castling_rights() {
+        let relaxed = |fen: &str| get_opening_from_fen_relaxed(fen).unwrap();
+
+        let opening = relaxed("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1");
+        assert_eq!(opening.name, "Starting Position");
+        assert!(opening.relaxed);
+
+        let opening = relaxed("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2");
+        assert_eq!(opening.name, "King's Pawn Game");
+        assert!(!opening.relaxed);
+        let opening = relaxed("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w Kq - 0 2");
+        assert_eq!(opening.name, "King's Pawn Game");
+        assert!(opening.relaxed);
+
+        // the Bongcloud king has really lost its rights
+        let opening = relaxed("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPPKPPP/RNBQ1BNR b KQkq - 1 2");
+        assert_eq!(opening.name, "Bongcloud Attack");
+        assert!(opening.relaxed);
+        assert!(get_opening_from_fen_relaxed(
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPPKPPP/RNBQ1BNR w kq - 1 2"
+        )
+        .is_err());
+        assert!(get_opening_from_fen(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1",
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_get_opening() {
+        let opening = get_opening_from_fen(
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 1 2",
+            None,
+        )
+        .unwrap();
+        assert_eq!(opening, "Bongcloud Attack");
+    }
+
+    #[test]
+    fn test_get_full_opening() {
+        let opening =
+            get_full_opening_from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 1 2")
+                .unwrap();
+        assert_eq!(opening.eco, "C20");
+        assert_eq!(opening.name, "Bongcloud Attack");
+    }
+
+    #[test]
+    fn test_lookup_ignores_move_counters() {
+        let opening = get_opening_from_fen(
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 0 1",
+            None,
+        )
+        .unwrap();
+        assert_eq!(opening, "Bongcloud Attack");
+
+        let opening = get_full_opening_from_fen(
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 7 30",
+        )
+        .unwrap();
+        assert_eq!(opening.name, "Bongcloud Attack");
+    }
+
+    #[test]
+    fn test_opening_accessors() {
+        let opening = find_by_name("Italian Game").unwrap();
+        assert_eq!(opening.eco(), "C50");
+        assert_eq!(opening.name(), "Italian Game");
+        assert_eq!(
+            opening.fen(),
+            "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3"
+        );
+        assert_eq!(opening.pgn(), Some("1. e4 e5 2. Nf3 Nc6 3. Bc4"));
+
+        assert_eq!(find_by_name("Empty Board").unwrap().pgn(), None);
+    }
+
+    #[test]
+    fn test_fen_from_opening_name() {
+        assert_eq!(
+            get_fen_from_opening_name("Italian Game").unwrap(),
+            "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3"
+        );
+        assert_eq!(
+            get_fen_from_opening_name("Starting Position").unwrap(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+        assert_eq!(
+            get_fen_from_opening_name("Empty Board").unwrap(),
+            "8/8/8/8/8/8/8/8 w - - 0 1"
+        );
+        assert!(matches!(
+            get_fen_from_opening_name("Nonexistent Opening"),
+            Err(Error::NoOpeningFound)
+        ));
+    }
+
+    #[test]
+    fn test_cached_fen() {
+        for name in ["Italian Game", "Starting Position", "Empty Board"] {
+            let opening = find_by_name(name).unwrap();
+            assert_eq!(
+                opening.fen(),
+                Fen::from_setup(opening.setup.clone()).to_string()
+            );
+        }
+        for opening in read_openings_index(INDEX_DATA[2]).unwrap() {
+            assert_eq!(
+                opening.fen(),
+                Fen::from_setup(opening.setup.clone()).to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn test_opening_round_trip() {
+        let opening = find_by_name("Italian Game").unwrap();
+        let json = serde_json::to_string(&opening).unwrap();
+        let parsed: Opening = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.eco(), opening.eco());
+        assert_eq!(parsed.name(), opening.name());
+        assert_eq!(parsed.fen(), opening.fen());
+        assert_eq!(parsed.pgn(), None);
+
+        let parsed: Opening = serde_json::from_str(
+            r#"{"eco":"B00","name":"King's Pawn","fen":"rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1","pgn":"1. e4"}"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.pgn(), Some("1. e4"));
+
+        assert!(serde_json::from_str::<Opening>(r#"{"eco":"","name":"","fen":"x"}"#).is_err());
+    }
+
+    #[test]
+    fn test_export_openings_json() {
+        let count = OPENINGS.iter().filter(|o| o.eco != "Extra").count();
+
+        let json = export_openings_json(None).unwrap();
+        let openings: Vec<Opening> = serde_json::from_str(&json).unwrap();
+        assert_eq!(openings.len(), count);
+        assert!(openings.iter().all(|o| o.pgn().is_none()));
+
+        let json = export_openings_json(Some(true)).unwrap();
+        let openings: Vec<Opening> = serde_json::from_str(&json).unwrap();
+        assert_eq!(openings.len(), count);
+        let italian = openings
+            .iter()
+            .find(|o| o.name() == "Italian Game")
+            .unwrap();
+        assert_eq!(italian.pgn(), Some("1. e4 e5 2. Nf3 Nc6 3. Bc4"));
+    }
+
+    #[test]
+    fn test_opening_turn() {
+        let json = serde_json::to_string(&find_by_name("King's Pawn Game").unwrap()).unwrap();
+        assert!(json.contains(r#""turn":"white""#));
+        let json = serde_json::to_string(&find_by_name("Italian Game").unwrap()).unwrap();
+        assert!(json.contains(r#""turn":"black""#));
+    }
+
+    #[test]
+    fn test_invalid_fen() {
+        let input = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPPKPPP w kq";
+        match get_opening_from_fen(input, None) {
+            Err(Error::InvalidFen { input: bad, .. }) => assert_eq!(bad, input),
+            other => panic!("expected InvalidFen, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_transposition() {
+        // 1. e4 c5 2. Nf3
+        let opening = get_opening_by_transposition(
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+        )
+        .unwrap();
+        assert_eq!(opening.eco, "B27");
+
+        // 1. Nf3 c5 2. e4
+        let transposed = get_opening_by_transposition(
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq e3 0 2",
+        )
+        .unwrap();
+        assert_eq!(transposed.eco, opening.eco);
+        assert_eq!(transposed.name, opening.name);
+    }
+
+    #[test]
+    fn test_opening_without_pgn() {
+        assert_eq!(get_opening_from_name("Starting Position").unwrap(), "");
+    }
+
+    #[test]
+    fn test_read_openings_skips_malformed_rows() {
+        let tsv = "eco\tname\tpgn\n\
+                   A00\tFirst\t1. e4\n\
+                   A00\tMissing Pgn\n\
+                   A00\tIllegal\t1. e5\n\
+                   A01\tSecond\t1. d4\n";
+        let (openings, skipped) = read_openings(tsv.as_bytes());
+        assert_eq!(skipped, 2);
+        let names: Vec<_> = openings.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, vec!["First", "Second"]);
+    }
+
+    #[test]
+    fn test_search_limit() {
+        let search = |limit| {
+            tauri::async_runtime::block_on(search_opening_name(
+                "Sicilian".to_string(),
+                limit,
+                None,
+                None,
+                None,
+                None,
+            ))
+        };
+        assert_eq!(search(Some(1)).unwrap().len(), 1);
+        assert_eq!(search(Some(50)).unwrap().len(), 50);
+        assert_eq!(search(None).unwrap().len(), DEFAULT_SEARCH_LIMIT);
+        assert_eq!(search(Some(10_000)).unwrap().len(), MAX_SEARCH_LIMIT);
+    }
+
+    #[test]
+    fn test_search_without_matches() {
+        let openings = tauri::async_runtime::block_on(search_opening_name(
+            "zzzzz".to_string(),
+            None,
+            None,
+            None,
+            None,
+            None,
+        ))
+        .unwrap();
+        assert!(openings.is_empty());
+    }
+
+    #[test]
+    fn test_search_blank_query() {
+        let search = |query: &str| {
+            tauri::async_runtime::block_on(search_opening_name(
+                query.to_string(),
+                None,
+                Some(0.0),
+                None,
+                None,
+                None,
+            ))
+            .unwrap()
+        };
+        assert!(search("").is_empty());
+        assert!(search(" \t  ").is_empty());
+
+        let names = |openings: Vec<Opening>| -> Vec<String> {
+            openings.into_iter().map(|o| o.name).collect()
+        };
+        assert_eq!(
+            names(search("  Italian Game ")),
+            names(search("Italian Game"))
+        );
+    }
+
+    #[test]
+    fn test_search_min_score() {
+        let search = |query: &str, limit, min_score| {
+            tauri::async_runtime::block_on(search_opening_name(
+                query.to_string(),
+                Some(limit),
+                Some(min_score),
+                None,
+                None,
+                None,
+            ))
+            .unwrap()
+        };
+        assert_eq!(search("Sicilian", 50, 0.7).len(), 50);
+        assert!(search("zzzzz", 50, 0.7).is_empty());
+        assert_eq!(search("zzzzz", 50, 0.0).len(), 50);
+
+        // the threshold only cuts the tail of the ranking
+        let strict = search("Sicilian Najdorf", 200, 0.9);
+        let loose = search("Sicilian Najdorf", 200, 0.0);
+        assert!(!strict.is_empty() && strict.len() < loose.len());
+        let names = |openings: &[Opening]| -> Vec<String> {
+            openings.iter().map(|o| o.name.clone()).collect()
+        };
+        assert_eq!(names(&strict), names(&loose[..strict.len()]));
+    }
+
+    #[test]
+    fn test_search_by_eco_code() {
+        let search = |query: &str| {
+            tauri::async_runtime::block_on(search_opening_name(
+                query.to_string(),
+                Some(20),
+                None,
+                None,
+                None,
+                None,
+            ))
+            .unwrap()
+        };
+        let openings = search("B90");
+        assert_eq!(openings.len(), 20);
+        assert_eq!(openings[0].name, "Sicilian Defense: Najdorf Variation");
+        assert!(openings[..10].iter().all(|o| o.eco == "B90"));
+
+        let openings = search("b9");
+        assert_eq!(openings.len(), 20);
+        assert!(openings.iter().all(|o| o.eco.starts_with("B9")));
+
+        let names = |openings: Vec<Opening>| -> Vec<String> {
+            openings.into_iter().map(|o| o.name).collect()
+        };
+        assert_eq!(
+            names(search("Najdorf")),
+            names(find_best_matches("Najdorf", 20, DEFAULT_MIN_SCORE))
+        );
+
+        assert!(is_eco_query(" C50 "));
+        assert!(!is_eco_query("F10"));
+        assert!(!is_eco_query("B900"));
+        assert!(!is_eco_query("B"));
+        assert!(!is_eco_query("Bxc6"));
+    }
+
+    #[test]
+    fn test_search_excludes_extra() {
+        let search = |include_extra| {
+            tauri::async_runtime::block_on(search_opening_name(
+                "Starting Position".to_string(),
+                Some(3),
+                None,
+                None,
+                include_extra,
+                None,
+            ))
+            .unwrap()
+        };
+        let openings = search(None);
+        assert_eq!(openings.len(), 3);
+        assert!(openings.iter().all(|o| o.eco != "Extra"));
+        assert_eq!(search(Some(false)).len(), 3);
+
+        let openings = search(Some(true));
+        assert_eq!(openings[0].name, "Starting Position");
+    }
+
+    #[test]
+    fn test_scored_search() {
+        for query in ["Sicilian Najdorf", "B90", "Gambit"] {
+            let matches = tauri::async_runtime::block_on(search_opening_name_scored(
+                query.to_string(),
+                Some(50),
+            ))
+            .unwrap();
+            assert!(!matches.is_empty());
+            assert!(matches.iter().all(|(_, score)| (0.0..=1.0).contains(score)));
+            assert!(matches.windows(2).all(|w| w[0].1 >= w[1].1));
+
+            let openings = tauri::async_runtime::block_on(search_opening_name(
+                query.to_string(),
+                Some(50),
+                None,
+                None,
+                None,
+                None,
+            ))
+            .unwrap();
+            let names: Vec<&str> = openings.iter().map(|o| o.name()).collect();
+            let scored_names: Vec<&str> = matches.iter().map(|(o, _)| o.name()).collect();
+            assert_eq!(names, scored_names);
+        }
+    }
+
+    #[test]
+    fn test_search_excluding() {
+        let search = |exclude_names: Vec<String>| -> Vec<String> {
+            tauri::async_runtime::block_on(search_opening_name_excluding(
+                "Sicilian Najdorf".to_string(),
+                exclude_names,
+                Some(5),
+            ))
+            .unwrap()
+            .into_iter()
+            .map(|o| o.name)
+            .collect()
+        };
+        let all = search(vec![]);
+        let top_six = tauri::async_runtime::block_on(search_opening_name(
+            "Sicilian Najdorf".to_string(),
+            Some(6),
+            None,
+            None,
+            None,
+            None,
+        ))
+        .unwrap();
+        assert_eq!(all.len(), 5);
+
+        let excluding_first = search(vec![all[0].clone()]);
+        assert_eq!(excluding_first.len(), 5);
+        assert!(!excluding_first.contains(&all[0]));
+        assert_eq!(excluding_first[..4], all[1..]);
+        assert_eq!(excluding_first[4], top_six[5].name);
+    }
+
+    #[test]
+    fn test_streamed_search() {
+        let mut emissions: Vec<(Vec<(Opening, f64)>, bool)> = Vec::new();
+        stream_search("Sicilian Najdorf", Some(10), |openings, finished| {
+            emissions.push((openings, finished));
+            Ok::<(), Error>(())
+        })
+        .unwrap();
+
+        assert!(emissions.len() > 1);
+        let (last, rest) = emissions.split_last().unwrap();
+        assert!(last.1);
+        assert!(rest.iter().all(|(_, finished)| !finished));
+        for (openings, _) in &emissions {
+            assert!(openings.windows(2).all(|w| w[0].1 >= w[1].1));
+        }
+        // a full page of matches only ever gets better
+        let worst: Vec<f64> = emissions
+            .iter()
+            .filter(|(openings, _)| openings.len() == 10)
+            .map(|(openings, _)| openings[9].1)
+            .collect();
+        assert!(worst.windows(2).all(|w| w[0] <= w[1]));
+
+        let scored = tauri::async_runtime::block_on(search_opening_name_scored(
+            "Sicilian Najdorf".to_string(),
+            Some(10),
+        ))
+        .unwrap();
+        let names = |openings: &[(Opening, f64)]| -> Vec<String> {
+            openings.iter().map(|(o, _)| o.name.clone()).collect()
+        };
+        assert_eq!(names(&last.0), names(&scored));
+
+        let result = stream_search("Sicilian", None, |_, _| Err("closed"));
+        assert_eq!(result, Err("closed"));
+
+        // the query is prepared like for the other searches
+        let stream = |query: &str| {
+            let mut emissions = Vec::new();
+            stream_search(query, Some(10), |openings, finished| {
+                emissions.push((openings, finished));
+                Ok::<(), Error>(())
+            })
+            .unwrap();
+            emissions
+        };
+        let blank = stream("   ");
+        assert_eq!(blank.len(), 1);
+        assert!(blank[0].0.is_empty() && blank[0].1);
+        let (last, _) = stream(" B90 ").pop().unwrap();
+        assert!(last
+            .iter()
+            .all(|(o, score)| o.eco == "B90" && *score == 1.0));
+        let long = format!("Sicilian Najdorf{}", " x".repeat(200));
+        assert_eq!(
+            names(&stream(&long).pop().unwrap().0),
+            names(
+                &stream(&format!("Sicilian Najdorf{}", " x".repeat(56)))
+                    .pop()
+                    .unwrap()
+                    .0
+            )
+        );
+    }
+
+    #[test]
+    fn test_search_in_family() {
+        let search = |family: &str, query: &str| {
+            tauri::async_runtime::block_on(search_opening_in_family(
+                family.to_string(),
+                query.to_string(),
+                Some(10),
+            ))
+        };
+        let matches = search("Sicilian Defense", "najdorf").unwrap();
+        assert!(!matches.is_empty());
+        assert!(matches[0]
+            .name
+            .starts_with("Sicilian Defense: Najdorf Variation"));
+        assert!(matches
+            .iter()
+            .all(|o| o.name.starts_with("Sicilian Defense")));
+
+        let matches = search("sicilian defense", "dragon").unwrap();
+        assert!(matches[0].name.contains("Dragon"));
+        assert!(matches!(
+            search("Not A Family", "najdorf"),
+            Err(Error::NoOpeningFound)
+        ));
+    }
+
+    #[test]
+    fn test_search_grouped() {
+        let flat = tauri::async_runtime::block_on(search_opening_name(
+            "Gambit".to_string(),
+            Some(50),
+            None,
+            None,
+            None,
+            None,
+        ))
+        .unwrap();
+        let groups =
+            tauri::async_runtime::block_on(search_opening_grouped("Gambit".to_string(), Some(50)))
+                .unwrap();
+        assert!(groups.len() > 1);
+        assert_eq!(
+            groups.iter().map(|(_, g)| g.len()).sum::<usize>(),
+            flat.len()
+        );
+
+        for (volume, openings) in &groups {
+            assert!(!openings.is_empty());
+            let expected: Vec<&str> = flat
+                .iter()
+                .filter(|o| eco_volume(&o.eco) == volume)
+                .map(|o| o.name.as_str())
+                .collect();
+            let names: Vec<&str> = openings.iter().map(|o| o.name.as_str()).collect();
+            assert_eq!(names, expected);
+        }
+    }
+
+    #[test]
+    fn test_autocomplete() {
+        let openings = autocomplete_opening("sicil".to_string(), 10).unwrap();
+        assert_eq!(openings.len(), 10);
+        assert_eq!(openings[0].name, "Sicilian Defense");
+        assert!(openings.iter().all(|o| o.name.starts_with("Sicilian")));
+        assert!(openings
+            .windows(2)
+            .all(|w| w[0].name.len() <= w[1].name.len()));
+
+        let openings = autocomplete_opening("GRUNF".to_string(), 1).unwrap();
+        assert!(openings[0].name.starts_with("Grünfeld"));
+
+        assert!(autocomplete_opening("  ".to_string(), 10)
+            .unwrap()
+            .is_empty());
+        assert!(autocomplete_opening("xyzzy".to_string(), 10).is_err());
+    }
+
+    #[test]
+    fn test_verify_opening_data() {
+        let report = verify_opening_data().unwrap();
+        assert!(report.unreplayable.is_empty());
+        assert!(report.duplicate_entries.is_empty());
+        assert!(report
+            .names_with_multiple_positions
+            .contains(&"Benko Gambit".to_string()));
+        assert!(!report.positions_with_multiple_names.is_empty());
+    }
+
+    #[test]
+    fn test_search_by_tag() {
+        let gambits = search_opening_by_tag("Gambit".to_string()).unwrap();
+        assert!(gambits.iter().any(|o| o.name == "Queen's Gambit Accepted"));
+        assert!(gambits.iter().all(|o| o
+            .name
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|w| w == "Gambit")));
+
+        let defenses = search_opening_by_tag("defense".to_string()).unwrap();
+        assert!(defenses.iter().any(|o| o.name == "Sicilian Defense"));
+        assert!(!defenses.iter().any(|o| o.name == "Italian Game"));
+
+        assert!(search_opening_by_tag("hedgehog".to_string())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_eco_volumes() {
+        let volumes = eco_volumes();
+        assert_eq!(volumes.len(), 5);
+        assert!(volumes
+            .iter()
+            .all(|v| v.count > 0 && !v.description.is_empty()));
+        assert_eq!(volumes[1].volume, "B");
+        assert_eq!(
+            volumes.iter().map(|v| v.count).sum::<usize>(),
+            OPENINGS.len() - EXTRA_OPENINGS.len()
+        );
+    }
+
+    #[test]
+    fn test_counts_by_volume() {
+        let counts = opening_counts_by_volume().unwrap();
+        assert_eq!(
+            counts.iter().map(|(_, count)| count).sum::<usize>(),
+            OPENINGS.len()
+        );
+
+        let volumes: Vec<&str> = counts.iter().map(|(volume, _)| volume.as_str()).collect();
+        assert_eq!(volumes, ["A", "B", "C", "D", "E", "Extra"]);
+        assert!(counts.iter().all(|(_, count)| *count > 0));
+        assert_eq!(counts[5].1, 2);
+    }
+
+    #[test]
+    fn test_export_sqlite() {
+        #[derive(diesel::QueryableByName)]
+        struct Row {
+            #[diesel(sql_type = Text)]
+            name: String,
+        }
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let count = export_openings_sqlite(file.path().to_path_buf()).unwrap();
+        assert_eq!(count, OPENINGS.len() - 2);
+
+        let mut db = SqliteConnection::establish(&file.path().to_string_lossy()).unwrap();
+        let rows: Vec<Row> = sql_query("SELECT name FROM Openings")
+            .load(&mut db)
+            .unwrap();
+        assert_eq!(rows.len(), OPENINGS.len() - 2);
+        assert!(rows.iter().any(|row| row.name == "Italian Game"));
+
+        // Exporting again replaces the table instead of appending to it
+        assert_eq!(
+            export_openings_sqlite(file.path().to_path_buf()).unwrap(),
+            count
+        );
+    }
+
+    fn by_score_then_name(a: &(Opening, f64), b: &(Opening, f64)) -> Ordering {
+        b.1.total_cmp(&a.1).then_with(|| a.0.name.cmp(&b.0.name))
+    }
+
+    /// Previous implementation, which sorted the matches on every insertion
+    fn find_best_matches_sorted(query: &str, limit: usize) -> Vec<Opening> {
+        let mut best_matches: Vec<(Opening, f64)> = Vec::new();
+
+        for opening in CANONICAL_INDICES.iter().map(|&i| OPENINGS[i]) {
+            if best_matches.iter().any(|(m, _)| m.name == opening.name) {
+                continue;
+            }
+
+            let query = normalize_name(query);
+            let query_tokens: Vec<&str> = name_tokens(&query).collect();
+            let name = normalize_name(&opening.name);
+            let score = name_score(
+                SimilarityMode::default(),
+                &query,
+                &query_tokens,
+                &sorted_words(&query),
+                &name,
+                &sorted_words(&name),
+            );
+
+            if best_matches.len() < limit {
+                best_matches.push((opening.clone(), score));
+                best_matches.sort_by(by_score_then_name);
+            } else {
+                let candidate = (opening.clone(), score);
+                if best_matches
+                    .last()
+                    .is_some_and(|worst| by_score_then_name(&candidate, worst) == Ordering::Less)
+                {
+                    best_matches.pop();
+                    best_matches.push(candidate);
+                    best_matches.sort_by(by_score_then_name);
+                }
+            }
+        }
+
+        best_matches.into_iter().map(|(o, _)| o).collect()
+    }
+
+    #[test]
+    fn test_heap_search_matches_sorted_search() {
+        let keys = |openings: Vec<Opening>| -> Vec<(String, String)> {
+            openings.into_iter().map(|o| (o.eco, o.name)).collect()
+        };
+        for query in ["Sicilian Najdorf", "Queen's Gambit", "Ruy", "xyz"] {
+            assert_eq!(
+                keys(find_best_matches(query, DEFAULT_SEARCH_LIMIT, 0.0)),
+                keys(find_best_matches_sorted(query, DEFAULT_SEARCH_LIMIT))
+            );
+        }
+    }
+
+    #[test]
+    fn test_search_returns_canonical_entries() {
+        // the longer A05 line comes first in the table
+        let name = "King's Indian Attack";
+        let fen = get_fen_from_opening_name(name).unwrap();
+        let openings = find_best_matches(name, DEFAULT_SEARCH_LIMIT, DEFAULT_MIN_SCORE);
+        let found = openings.iter().find(|o| o.name == name).unwrap();
+        assert_eq!(found.eco, "A07");
+        assert_eq!(found.fen(), fen);
+
+        let completed = autocomplete_opening(name.to_string(), MAX_SEARCH_LIMIT).unwrap();
+        assert_eq!(
+            completed.iter().find(|o| o.name == name).unwrap().eco,
+            "A07"
+        );
+    }
+
+    #[test]
+    fn test_equal_scores_sorted_by_name() {
+        // every name scores 0 against an empty query
+        let names: Vec<String> = find_best_matches("", 5, 0.0)
+            .into_iter()
+            .map(|o| o.name)
+            .collect();
+        let mut expected: Vec<String> = OPENINGS.iter().map(|o| o.name.clone()).collect();
+        expected.sort();
+        expected.dedup();
+        expected.truncate(5);
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn test_search_by_eco() {
+        let openings = search_opening_by_eco("C20".to_string()).unwrap();
+        assert!(openings.iter().all(|o| o.eco == "C20"));
+        assert!(openings.iter().any(|o| o.name == "Bongcloud Attack"));
+        assert!(openings.windows(2).all(|w| w[0].name <= w[1].name));
+
+        assert!(search_opening_by_eco("Z99".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_search_by_eco_range() {
+        let openings = search_opening_by_eco_range("B20".to_string(), "B99".to_string()).unwrap();
+        assert!(openings
+            .iter()
+            .all(|o| o.eco.as_str() >= "B20" && o.eco.as_str() <= "B99"));
+        assert!(openings
+            .iter()
+            .any(|o| o.name == "Sicilian Defense: Najdorf Variation"));
+        assert!(openings
+            .windows(2)
+            .all(|w| (&w[0].eco, &w[0].name) <= (&w[1].eco, &w[1].name)));
+
+        let all = search_opening_by_eco_range("A00".to_string(), "Z99".to_string()).unwrap();
+        assert!(all.iter().all(|o| o.eco != "Extra"));
+    }
+
+    #[test]
+    fn test_opening_moves() {
+        let moves = get_opening_moves("Italian Game").unwrap();
+        assert_eq!(moves.san, vec!["e4", "e5", "Nf3", "Nc6", "Bc4"]);
+        assert_eq!(moves.uci, vec!["e2e4", "e7e5", "g1f3", "b8c6", "f1c4"]);
+
+        let moves = get_opening_moves("Empty Board").unwrap();
+        assert!(moves.san.is_empty() && moves.uci.is_empty());
+    }
+
+    #[test]
+    fn test_name_typography() {
+        let pgn = get_opening_from_name("Caro-Kann Defense").unwrap();
+        assert_eq!(
+            get_opening_from_name("Caro\u{2013}Kann Defense").unwrap(),
+            pgn
+        );
+        assert_eq!(
+            get_opening_from_name("Caro\u{2014}Kann Defense").unwrap(),
+            pgn
+        );
+        assert_eq!(
+            get_opening_from_name("  Caro-Kann   Defense ").unwrap(),
+            pgn
+        );
+        assert!(get_opening_from_name("CaroKann Defense").is_err());
+
+        // the name shown to the user is the one from the table
+        assert_eq!(
+            find_by_name("Caro\u{2013}Kann  Defense").unwrap().name,
+            "Caro-Kann Defense"
+        );
+        assert_eq!(
+            canonical_name("Caro \u{2013} Kann\tDefense"),
+            "Caro - Kann Defense"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_names_keep_shortest_line() {
+        let tsv = "eco\tname\tpgn\n\
+                   C40\tSame Name\t1. e4 e5 2. Nf3 Nc6\n\
+                   C20\tSame Name\t1. e4 e5\n\
+                   C44\tSame Name\t1. e4 e5 2. Nf3 Nf6\n\
+                   A00\tOther Name\t1. h4\n";
+        let (openings, _) = read_openings(tsv.as_bytes());
+        let index = index_names(&openings.iter().collect::<Vec<_>>());
+        assert_eq!(index.len(), 2);
+        assert_eq!(openings[index["Same Name"]].eco, "C20");
+        assert_eq!(openings[index["Other Name"]].eco, "A00");
+
+        assert_eq!(
+            get_opening_from_name("Ruy Lopez: Morphy Defense").unwrap(),
+            "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6"
+        );
+    }
+
+    #[test]
+    fn test_openings_after_first_move() {
+        let names = |san: &str| -> HashSet<String> {
+            openings_after_first_move(san.to_string())
+                .unwrap()
+                .into_iter()
+                .map(|o| o.name)
+                .collect()
+        };
+        let e4 = names("e4");
+        let d4 = names("d4");
+        assert!(e4.contains("Italian Game"));
+        assert!(d4.contains("Queen's Gambit"));
+        assert!(e4.is_disjoint(&d4));
+
+        let openings = openings_after_first_move("Nf3".to_string()).unwrap();
+        assert!(!openings.is_empty());
+        assert!(openings.windows(2).all(|w| w[0].eco <= w[1].eco));
+
+        assert!(openings_after_first_move("e5".to_string()).is_err());
+        assert!(openings_after_first_move("Zz9".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_opening_siblings() {
+        let names = |name: &str| {
+            let (prev, next) = opening_siblings(name.to_string()).unwrap();
+            (prev.map(|o| o.name), next.map(|o| o.name))
+        };
+        let double = "English Opening: Symmetrical Variation, Double Fianchetto";
+        let duchamp = "English Opening: Symmetrical Variation, Duchamp Variation";
+        let full = "English Opening: Symmetrical Variation, Full Symmetry Line";
+
+        assert_eq!(
+            names(duchamp),
+            (Some(double.to_string()), Some(full.to_string()))
+        );
+        assert_eq!(names(double), (None, Some(duchamp.to_string())));
+        assert_eq!(names(full), (Some(duchamp.to_string()), None));
+        assert!(opening_siblings("Not An Opening".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_opening_line_fens() {
+        let fens = opening_line_fens("Italian Game".to_string()).unwrap();
+        assert_eq!(fens.len(), 6);
+        assert_eq!(
+            fens[0],
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+        assert_eq!(
+            fens[1],
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+        assert_eq!(
+            fens.last().unwrap(),
+            find_by_name("Italian Game").unwrap().fen()
+        );
+
+        assert_eq!(
+            opening_line_fens("Empty Board".to_string()).unwrap(),
+            ["8/8/8/8/8/8/8/8 w - - 0 1"]
+        );
+        assert!(opening_line_fens("Not An Opening".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_random_opening() {
+        let opening = random_opening(Some(8), Some('B'), Some(42)).unwrap();
+        assert!(opening.eco.starts_with('B'));
+        assert!(opening_line(&opening).len() >= 8);
+        let again = random_opening(Some(8), Some('b'), Some(42)).unwrap();
+        assert_eq!(again.name, opening.name);
+
+        assert!(random_opening(None, None, None).unwrap().eco != "Extra");
+        assert!(matches!(
+            random_opening(Some(1000), None, Some(42)),
+            Err(Error::NoOpeningFound)
+        ));
+    }
+
+    #[test]
+    fn test_opening_of_the_day() {
+        let opening = opening_of_the_day("2024-03-15".to_string()).unwrap();
+        assert_ne!(opening.eco, "Extra");
+        assert!(opening_line(&opening).len() >= OPENING_OF_THE_DAY_MIN_DEPTH);
+        assert_eq!(
+            opening_of_the_day("2024-03-15".to_string()).unwrap().name,
+            opening.name
+        );
+
+        let names: HashSet<String> = (1..=28)
+            .map(|day| {
+                opening_of_the_day(format!("2024-02-{:02}", day))
+                    .unwrap()
+                    .name
+            })
+            .collect();
+        assert!(names.len() > 20);
+
+        assert!(matches!(
+            opening_of_the_day("15/03/2024".to_string()),
+            Err(Error::ParseDate(_))
+        ));
+        assert!(opening_of_the_day("2024-02-30".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_openings_with_pawn_structure() {
+        // the isolated queen pawn of the Tarrasch Defense, with only the kings left
+        let openings = openings_with_pawn_structure(
+            "4k3/pp3pp1/7p/3p4/8/6P1/PP2PP1P/4K3 w - - 0 1".to_string(),
+        )
+        .unwrap();
+        let names: Vec<&str> = openings.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Tarrasch Defense: Classical Variation, Chandler Variation",
+                "Tarrasch Defense: Classical Variation, Main Line",
+                "Tarrasch Defense: Classical Variation, Spassky Variation",
+            ]
+        );
+
+        let openings =
+            openings_with_pawn_structure("4k3/8/8/8/PPPPPPPP/8/8/4K3 w - - 0 1".to_string());
+        assert!(openings.unwrap().is_empty());
+        assert!(openings_with_pawn_structure("not a fen".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_eco_codes_for_name() {
+        let codes = eco_codes_for_name("Sicilian Defense".to_string()).unwrap();
+        assert_eq!(codes.len(), 81);
+        assert_eq!(codes.first().unwrap(), "B20");
+        assert_eq!(codes[codes.len() - 2], "B99");
+        // the Marshall Gambit transposes to a French Defense
+        assert_eq!(codes.last().unwrap(), "C10");
+        assert!(codes.windows(2).all(|w| w[0] < w[1]));
+
+        assert_eq!(
+            eco_codes_for_name("Bongcloud Attack".to_string()).unwrap(),
+            ["C20"]
+        );
+        assert!(eco_codes_for_name("Not An Opening".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_eco_for_game() {
+        let game = moves("e4 e5 Nf3 Nc6 Bc4 Bc5 Kf1 Kf8 Ke1");
+        let mut pos = Chess::default();
+        for mv in &game {
+            let m = parse_game_move(&pos, 0, mv).unwrap();
+            pos.play_unchecked(&m);
+        }
+        assert!(get_full_opening_from_setup(pos.into_setup(EnPassantMode::Legal)).is_err());
+        assert_eq!(eco_for_game(game).unwrap(), "C50");
+
+        assert_eq!(eco_for_game(moves("d4 d5 c4")).unwrap(), "D06");
+        assert!(matches!(
+            eco_for_game(Vec::new()),
+            Err(Error::PositionNotNamed)
+        ));
+        assert!(eco_for_game(moves("e4 e4")).is_err());
+    }
+
+    #[test]
+    fn test_nearest_named_opening() {
+        let nearest = nearest_named_opening(moves("e4 e5 Nf3 Nc6 Bc4 Bc5 Kf1 Kf8 Ke1")).unwrap();
+        assert_eq!(nearest.opening.name, "Italian Game: Giuoco Piano");
+        assert_eq!(nearest.extra_moves, moves("Kf1 Kf8 Ke1"));
+
+        let nearest = nearest_named_opening(moves("e4 e5 Nf3 Nc6 Bc4")).unwrap();
+        assert_eq!(nearest.opening.name, "Italian Game");
+        assert!(nearest.extra_moves.is_empty());
+
+        let nearest = nearest_named_opening(Vec::new()).unwrap();
+        assert_eq!(nearest.opening.name, "Starting Position");
+
+        assert!(matches!(
+            nearest_named_opening(moves("e4 e5 Ke3")),
+            Err(Error::IllegalMoveAtPly(3, _))
+        ));
+    }
+
+    #[test]
+    fn test_opening_children() {
+        let children = get_opening_children("King's Pawn").unwrap();
+        let names: Vec<&str> = children.iter().map(|o| o.name.as_str()).collect();
+        assert!(names.contains(&"Sicilian Defense"));
+        assert!(names.contains(&"French Defense"));
+        assert!(names.contains(&"King's Pawn Game"));
+        assert!(!names.contains(&"King's Pawn"));
+        assert!(children.iter().all(|o| opening_line(o).len() == 2));
+    }
+
+    #[test]
+    fn test_opening_subtree() {
+        let names = |openings: Vec<Opening>| -> HashSet<String> {
+            openings.into_iter().map(|o| o.name).collect()
+        };
+        let subtree = opening_subtree("King's Pawn".to_string(), 1).unwrap();
+        assert_eq!(subtree[0].name, "King's Pawn");
+        let mut children = names(get_opening_children("King's Pawn").unwrap());
+        children.insert("King's Pawn".to_string());
+        assert_eq!(names(subtree), children);
+
+        let subtree = opening_subtree("King's Pawn".to_string(), 2).unwrap();
+        assert!(subtree.iter().skip(1).all(|o| {
+            let depth = opening_line(o).len();
+            (2..=3).contains(&depth) && opening_line(o)[0] == "e4"
+        }));
+        let deeper = names(subtree);
+        assert!(deeper.is_superset(&children));
+        assert!(deeper.contains("King's Knight Opening"));
+        assert!(!deeper.contains("Italian Game"));
+
+        let root = opening_subtree("Italian Game".to_string(), 0).unwrap();
+        assert_eq!(names(root), HashSet::from(["Italian Game".to_string()]));
+        assert!(opening_subtree("Not An Opening".to_string(), 1).is_err());
+    }
+
+    #[test]
+    fn test_common_opening_ancestor() {
+        let ancestor = |a: &str, b: &str| {
+            common_opening_ancestor(a.to_string(), b.to_string())
+                .unwrap()
+                .map(|o| o.name)
+        };
+        assert_eq!(
+            ancestor(
+                "Sicilian Defense: Alapin Variation",
+                "Sicilian Defense: Closed"
+            ),
+            Some("Sicilian Defense".to_string())
+        );
+        assert_eq!(
+            ancestor("Sicilian Defense", "Sicilian Defense: Alapin Variation"),
+            Some("Sicilian Defense".to_string())
+        );
+        assert_eq!(
+            ancestor("Italian Game", "Ruy Lopez"),
+            Some("King's Knight Opening: Normal Variation".to_string())
+        );
+        assert_eq!(ancestor("Italian Game", "Queen's Gambit"), None);
+        assert!(common_opening_ancestor("Italian Game".to_string(), "Nope".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_suggest_next_opening_moves() {
+        let suggestions = suggest_next_opening_moves(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string(),
+        )
+        .unwrap();
+        let suggestion = |san: &str| {
+            suggestions
+                .iter()
+                .find(|(s, _)| s == san)
+                .map(|(_, o)| o.name.as_str())
+        };
+        assert_eq!(suggestions.len(), 20);
+        assert_eq!(suggestion("e4"), Some("King's Pawn"));
+        assert_eq!(suggestion("d4"), Some("Queen's Pawn Game"));
+
+        let suggestions = suggest_next_opening_moves(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3".to_string(),
+        )
+        .unwrap();
+        assert!(suggestions
+            .iter()
+            .any(|(san, o)| san == "Bc5" && o.name.starts_with("Italian Game")));
+
+        let out_of_book = "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N2N2/PP2BPPP/R2QKB1R w KQ - 4 9";
+        assert!(suggest_next_opening_moves(out_of_book.to_string())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_opening_depth() {
+        let depth = |name: &str| opening_depth(name.to_string()).unwrap();
+        assert_eq!(
+            depth("Ruy Lopez: Marshall Attack, Main Line, Spassky Variation"),
+            36
+        );
+        assert_eq!(depth("Italian Game"), 5);
+        assert_eq!(depth("Starting Position"), 0);
+        assert_eq!(depth("Empty Board"), 0);
+        assert!(matches!(
+            opening_depth("Nonexistent Opening".to_string()),
+            Err(Error::NoOpeningFound)
+        ));
+    }
+
+    #[test]
+    fn test_resolve_opening_alias() {
+        let resolve = |query: &str| resolve_opening_alias(query.to_string()).unwrap();
+        assert_eq!(resolve("Spanish"), "Ruy Lopez");
+        assert_eq!(resolve("Spanish Game"), "Ruy Lopez");
+        assert_eq!(
+            resolve("spanish game: exchange variation"),
+            "Ruy Lopez: Exchange Variation"
+        );
+        assert_eq!(resolve("Petroff"), "Russian Game");
+        assert_eq!(resolve("Petrov's Defense"), "Russian Game");
+        // unknown variations fall back to the family
+        assert_eq!(resolve("Petroff: Made Up Variation"), "Russian Game");
+
+        assert_eq!(resolve("ruy lopez"), "Ruy Lopez");
+        assert_eq!(resolve("  reti opening "), "Réti Opening");
+        assert!(matches!(
+            resolve_opening_alias("Nonexistent Opening".to_string()),
+            Err(Error::NoOpeningFound)
+        ));
+    }
+
+    #[test]
+    fn test_opening_parent() {
+        let parent = get_opening_parent("Ruy Lopez: Exchange Variation").unwrap();
+        assert_eq!(parent.unwrap().name, "Ruy Lopez: Morphy Defense");
+
+        let parent = get_opening_parent("King's Pawn").unwrap();
+        assert_eq!(parent.unwrap().name, "Starting Position");
+
+        assert!(get_opening_parent("Empty Board").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_opening_transition_move() {
+        let transition = |parent: &str, child: &str| {
+            opening_transition_move(parent.to_string(), child.to_string())
+        };
+        assert_eq!(
+            transition("Ruy Lopez: Morphy Defense", "Ruy Lopez: Exchange Variation").unwrap(),
+            "Bxc6"
+        );
+        assert_eq!(
+            transition("Starting Position", "King's Pawn").unwrap(),
+            "e4"
+        );
+
+        for (parent, child) in [
+            ("Italian Game", "French Defense"),
+            ("Ruy Lopez: Exchange Variation", "Ruy Lopez: Morphy Defense"),
+            ("King's Pawn", "King's Pawn"),
+            ("Starting Position", "King's Pawn Game"),
+            ("Empty Board", "King's Pawn"),
+        ] {
+            assert!(matches!(
+                transition(parent, child),
+                Err(Error::NotAContinuation)
+            ));
+        }
+        assert!(matches!(
+            transition("Nonexistent Opening", "King's Pawn"),
+            Err(Error::NoOpeningFound)
+        ));
+    }
+
+    #[test]
+    fn test_opening_tree() {
+        let tree = export_opening_tree().unwrap();
+        assert_eq!(tree.len(), OPENINGS.len());
+        let parent_name = |name: &str| -> Option<&str> {
+            let node = &tree[OPENINGS_BY_NAME[name]];
+            node.parent.map(|i| tree[i].opening.name.as_str())
+        };
+        assert_eq!(
+            parent_name("Ruy Lopez: Exchange Variation"),
+            Some("Ruy Lopez: Morphy Defense")
+        );
+        assert_eq!(parent_name("King's Pawn"), Some("Starting Position"));
+        // 1. a3 a5 has no name of its own
+        assert_eq!(
+            parent_name("Anderssen's Opening: Polish Gambit"),
+            Some("Anderssen's Opening")
+        );
+        assert_eq!(parent_name("Starting Position"), None);
+        assert_eq!(parent_name("Empty Board"), None);
+
+        for node in &tree {
+            if let Some(parent) = node.parent {
+                assert!(tree[parent].depth < node.depth);
+            }
+        }
+    }
+
+    #[test]
+    fn test_search_ignores_accents_and_case() {
+        let openings = find_best_matches("reti", DEFAULT_SEARCH_LIMIT, DEFAULT_MIN_SCORE);
+        assert_eq!(openings[0].name, "Réti Opening");
+
+        let openings = find_best_matches("CARO-KANN", DEFAULT_SEARCH_LIMIT, DEFAULT_MIN_SCORE);
+        assert_eq!(openings[0].name, "Caro-Kann Defense");
+    }
+
+    #[test]
+    fn test_search_swapped_words() {
+        let top = |query: &str| {
+            find_best_matches(query, 3, DEFAULT_MIN_SCORE)[0]
+                .name
+                .clone()
+        };
+        assert_eq!(top("Defense Sicilian"), "Sicilian Defense");
+        assert_eq!(top("Attack Bongcloud"), "Bongcloud Attack");
+        assert_eq!(top("gambit queen's"), "Queen's Gambit");
+        assert_eq!(top("game italian"), "Italian Game");
+
+        // an exact wording still scores 1
+        assert_eq!(
+            name_score(
+                SimilarityMode::JaroWinkler,
+                "sicilian defense",
+                &["sicilian", "defense"],
+                "defense sicilian",
+                "sicilian defense",
+                "defense sicilian"
+            ),
+            1.0
+        );
+        assert_eq!(
+            sorted_words("queen's gambit: declined"),
+            "declined gambit queen s"
+        );
+    }
+
+    #[test]
+    fn test_search_long_query() {
+        let search = |query: String| {
+            tauri::async_runtime::block_on(search_opening_name(query, None, None, None, None, None))
+                .unwrap()
+        };
+        let query = format!("Sicilian Defense {}", "x".repeat(10_000));
+        let start = Instant::now();
+        let openings = search(query.clone());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(openings[0].name, "Sicilian Defense");
+
+        let prefix: String = query.chars().take(MAX_QUERY_CHARS).collect();
+        let names = |openings: Vec<Opening>| -> Vec<String> {
+            openings.into_iter().map(|o| o.name).collect()
+        };
+        assert_eq!(names(openings), names(search(prefix)));
+        assert_eq!(
+            char_prefix(&"é".repeat(200), MAX_QUERY_CHARS)
+                .chars()
+                .count(),
+            MAX_QUERY_CHARS
+        );
+    }
+
+    #[test]
+    fn test_search_similarity_modes() {
+        for mode in [
+            SimilarityMode::JaroWinkler,
+            SimilarityMode::Levenshtein,
+            SimilarityMode::Sorensen,
+        ] {
+            let search = |query: &str| {
+                tauri::async_runtime::block_on(search_opening_name(
+                    query.to_string(),
+                    Some(3),
+                    Some(0.0),
+                    None,
+                    None,
+                    Some(mode),
+                ))
+                .unwrap()
+            };
+            let names: Vec<String> = search("Ruy Lopez").into_iter().map(|o| o.name).collect();
+            assert_eq!(names, ["Ruy Lopez", "Ruy Lopez: Open", "Ruy Lopez: Closed"]);
+            assert_eq!(search("sicilan defense")[0].name, "Sicilian Defense");
+            assert_eq!(search("defense sicilian")[0].name, "Sicilian Defense");
+        }
+    }
+
+    #[test]
+    fn test_search_distinctive_word() {
+        let openings = find_best_matches("najdorf", DEFAULT_SEARCH_LIMIT, DEFAULT_MIN_SCORE);
+        assert!(openings[0]
+            .name
+            .starts_with("Sicilian Defense: Najdorf Variation"));
+
+        let openings = find_best_matches("dragon", DEFAULT_SEARCH_LIMIT, DEFAULT_MIN_SCORE);
+        assert!(openings[0]
+            .name
+            .starts_with("Sicilian Defense: Dragon Variation"));
+    }
+
+    #[test]
+    fn test_popularity_breaks_ties() {
+        let names = |tsv: &str| -> Vec<String> {
+            let mut user = UserOpenings::default();
+            user.load(tsv.as_bytes()).unwrap();
+            user.find_best_matches("my flank line", 2, DEFAULT_MIN_SCORE)
+                .into_iter()
+                .map(|o| o.name)
+                .collect()
+        };
+        let tsv = "eco\tname\tpgn\n\
+                   A00\tMy Flank Line A\t1. h4\n\
+                   A00\tMy Flank Line B\t1. a4\n";
+        assert_eq!(names(tsv), ["My Flank Line A", "My Flank Line B"]);
+
+        let tsv = "eco\tname\tpgn\tpopularity\n\
+                   A00\tMy Flank Line A\t1. h4\t10\n\
+                   A00\tMy Flank Line B\t1. a4\t5000\n";
+        assert_eq!(names(tsv), ["My Flank Line B", "My Flank Line A"]);
+
+        // popularity doesn't outweigh a clearly better match
+        let tsv = "eco\tname\tpgn\tpopularity\n\
+                   A00\tMy Flank Line\t1. h4\t0\n\
+                   A00\tMy Flank Line Variation\t1. a4\t1000000\n";
+        assert_eq!(names(tsv), ["My Flank Line", "My Flank Line Variation"]);
+        assert!(popularity_bonus(u32::MAX) <= POPULARITY_WEIGHT);
+    }
+
+    #[test]
+    fn test_cancelled_search() {
+        let user = UserOpenings::default();
+        let checks = AtomicUsize::new(0);
+        // a newer query arrives while the first slice of names is scored
+        let superseded = || checks.fetch_add(1, atomic::Ordering::Relaxed) > 0;
+        assert!(user
+            .find_best_matches_until("Sicilian", 10, 0.0, SimilarityMode::default(), &[], |_| {
+                superseded()
+            })
+            .is_none());
+        assert_eq!(checks.load(atomic::Ordering::Relaxed), 2);
+
+        let matches = user
+            .find_best_matches_until("Sicilian", 10, 0.0, SimilarityMode::default(), &[], |_| {
+                false
+            })
+            .unwrap();
+        assert_eq!(matches.len(), 10);
+
+        let search = |caller: &str, generation| {
+            tauri::async_runtime::block_on(search_opening_name(
+                "Sicilian".to_string(),
+                None,
+                None,
+                Some(SearchGeneration {
+                    caller: caller.to_string(),
+                    generation,
+                }),
+                None,
+                None,
+            ))
+        };
+        assert!(search("test_search_cancelled", 5).is_ok());
+        assert!(matches!(
+            search("test_search_cancelled", 3),
+            Err(Error::SearchCancelled)
+        ));
+        // another caller's searches don't cancel these
+        assert!(search("test_search_cancelled_other", 1).is_ok());
+        assert!(next_search_generation() < next_search_generation());
+
+        let mut latest = HashMap::new();
+        let last = 2 * MAX_SEARCH_CALLERS as u64 - 1;
+        for generation in 0..=last {
+            let search = SearchGeneration {
+                caller: format!("caller {generation}"),
+                generation,
+            };
+            track_search(&mut latest, &search);
+        }
+        assert_eq!(latest.len(), MAX_SEARCH_CALLERS);
+        assert!(!latest.contains_key("caller 0"));
+        assert_eq!(latest[&format!("caller {last}")], last);
+    }
+
+    #[test]
+    fn test_opening_from_hash() {
+        let start = position_hash(Setup::default()).unwrap();
+        assert_eq!(start, 0x463b96181691fc9c);
+        for hash in ["0x463b96181691fc9c", "5060803636482931868"] {
+            assert_eq!(
+                get_opening_from_hash(hash.to_string()).unwrap().name,
+                "Starting Position"
+            );
+        }
+
+        for fen in [
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 1 2",
+            "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3",
+        ] {
+            let setup = parse_fen(fen).unwrap().into_setup();
+            let hash = position_hash(setup).unwrap();
+            assert_eq!(
+                get_opening_from_hash(format!("{:#x}", hash)).unwrap().name,
+                get_opening_from_fen(fen, None).unwrap()
+            );
+        }
+
+        assert!(position_hash(Setup::empty()).is_none());
+        assert!(matches!(
+            get_opening_from_hash("0".to_string()),
+            Err(Error::PositionNotNamed)
+        ));
+        assert!(matches!(
+            get_opening_from_hash("0x463b9618169zfc9c".to_string()),
+            Err(Error::ParseInt(_))
+        ));
+    }
+
+    #[test]
+    fn test_all_openings_from_fen() {
+        let openings =
+            get_all_openings_from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 1 2")
+                .unwrap();
+        assert_eq!(openings.len(), 1);
+        assert_eq!(openings[0].name, "Bongcloud Attack");
+
+        let mut counts: HashMap<Setup, usize> = HashMap::new();
+        for opening in OPENINGS.iter() {
+            *counts
+                .entry(normalize_setup(opening.setup.clone()))
+                .or_default() += 1;
+        }
+        for (setup, count) in &counts {
+            let fen = Fen::from_setup(setup.clone()).to_string();
+            let openings = get_all_openings_from_fen(&fen).unwrap();
+            assert_eq!(openings.len(), *count);
+            assert_eq!(openings_by_setup(setup).len(), *count);
+            assert!(openings
+                .iter()
+                .all(|o| normalize_setup(o.setup.clone()) == *setup));
+            assert!(openings.windows(2).all(|w| w[0].eco <= w[1].eco));
+        }
+    }
+
+    #[test]
+    fn test_shortest_named_line() {
+        // the knights going back and forth transpose to the king's pawn opening
+        let tsv = "eco\tname\tpgn\n\
+                   A00\tKnight Dance\t1. Nf3 Nf6 2. Ng1 Ng8 3. e4\n\
+                   B00\tKing's Pawn\t1. e4\n";
+        let (openings, _) = read_openings(tsv.as_bytes());
+        assert_eq!(
+            normalize_setup(openings[0].setup.clone()),
+            normalize_setup(openings[1].setup.clone())
+        );
+        assert_eq!(shortest_line(&openings).unwrap().name, "King's Pawn");
+
+        for name in [
+            "Italian Game",
+            "Queen's Gambit Declined",
+            "Sicilian Defense: Najdorf Variation",
+        ] {
+            let fen = get_fen_from_opening_name(name).unwrap();
+            let shortest = shortest_named_line_for_fen(fen.clone()).unwrap();
+            let depth = |o: &Opening| opening_line(o).len();
+            assert!(get_all_openings_from_fen(&fen)
+                .unwrap()
+                .iter()
+                .all(|o| depth(o) >= depth(&shortest)));
+        }
+        assert!(matches!(
+            shortest_named_line_for_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1".to_string()),
+            Err(Error::PositionNotNamed)
+        ));
+    }
+
+    #[test]
+    fn test_truncate_opening_name() {
+        let truncate = |name: &str, max_chars| truncate_opening_name(name.to_string(), max_chars);
+        assert_eq!(truncate("Réti Opening", 12), "Réti Opening");
+        assert_eq!(truncate("Réti Opening", 11), "Réti Openi…");
+        assert_eq!(truncate("Réti Opening", 6), "Réti…");
+        assert_eq!(truncate("Réti Opening", 3), "Ré…");
+        assert_eq!(truncate("Réti Opening", 1), "…");
+        assert_eq!(truncate("Réti Opening", 0), "");
+        assert_eq!(
+            truncate("Grünfeld Defense: Exchange Variation", 16),
+            "Grünfeld Defens…"
+        );
+
+        // a decomposed accent stays with its letter
+        assert_eq!(truncate("Re\u{301}ti Opening", 3), "R…");
+        assert_eq!(truncate("Re\u{301}ti Opening", 4), "Re\u{301}…");
+        for max_chars in 0..20 {
+            assert!(truncate("Réti Opening", max_chars).chars().count() <= max_chars);
+        }
+    }
+
+    #[test]
+    fn test_opening_synonyms() {
+        let tsv = "eco\tname\tpgn\n\
+                   A00\tKnight Dance\t1. Nf3 Nf6 2. Ng1 Ng8 3. e4\n\
+                   B00\tKing's Pawn\t1. e4\n\
+                   B00\tKing's Pawn\t1. e4\n\
+                   C20\tKing's Pawn Game\t1. e4 e5\n";
+        let (openings, _) = read_openings(tsv.as_bytes());
+        let setup = normalize_setup(openings[1].setup.clone());
+        let same = openings
+            .iter()
+            .filter(|o| normalize_setup(o.setup.clone()) == setup);
+        assert_eq!(other_names("King's Pawn", same), ["Knight Dance"]);
+
+        // the en passant capture left after 3. e4 tells these two apart
+        assert!(
+            opening_synonyms("Van Geet Opening: Nowokunski Gambit".to_string())
+                .unwrap()
+                .is_empty()
+        );
+        assert!(opening_synonyms("Italian Game".to_string())
+            .unwrap()
+            .is_empty());
+        assert!(opening_synonyms("Not An Opening".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_opening_from_board_placement() {
+        let placement = |placement: &str, turn: &str| {
+            get_opening_from_board_placement(placement.to_string(), turn.to_string())
+        };
+        let italian = "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R";
+        assert_eq!(placement(italian, "b").unwrap().name, "Italian Game");
+        assert_eq!(placement(italian, "black").unwrap().name, "Italian Game");
+        assert!(matches!(
+            placement(italian, "w"),
+            Err(Error::PositionNotNamed)
+        ));
+
+        // the Bongcloud position is found whatever the castling rights
+        let bongcloud = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPPKPPP/RNBQ1BNR";
+        assert_eq!(placement(bongcloud, "b").unwrap().name, "Bongcloud Attack");
+        assert_eq!(
+            placement("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR", "w")
+                .unwrap()
+                .name,
+            "Starting Position"
+        );
+        assert!(matches!(
+            placement(italian, "x"),
+            Err(Error::InvalidFen { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unnamed_position() {
+        let middlegame: Fen = "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N2N2/PP2BPPP/R2QKB1R w KQ - 4 9"
+            .parse()
+            .unwrap();
+        assert!(matches!(
+            get_opening_from_setup(middlegame.into_setup(), GameVariant::Standard),
+            Err(Error::PositionNotNamed)
+        ));
+        assert!(matches!(
+            get_all_openings_from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1"),
+            Err(Error::PositionNotNamed)
+        ));
+        assert!(matches!(
+            get_opening_from_name("Nonexistent Opening"),
+            Err(Error::NoOpeningFound)
+        ));
+    }
+
+    #[test]
+    fn test_openings_from_names() {
+        let names = [
+            "Italian Game",
+            "Nonexistent Opening",
+            "Starting Position",
+            "Ruy Lopez",
+        ];
+        let pgns = get_openings_from_names(names.map(String::from).to_vec()).unwrap();
+        assert_eq!(
+            pgns,
+            [
+                Some("1. e4 e5 2. Nf3 Nc6 3. Bc4".to_string()),
+                None,
+                None,
+                Some("1. e4 e5 2. Nf3 Nc6 3. Bb5".to_string()),
+            ]
+        );
+        assert!(get_openings_from_names(Vec::new()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let suggestion = |name: &str| match get_opening_from_name(name) {
+            Err(Error::NoOpeningFoundDidYouMean { suggestion }) => Some(suggestion),
+            _ => None,
+        };
+        assert_eq!(
+            suggestion("Sicilan Defense").as_deref(),
+            Some("Sicilian Defense")
+        );
+        assert_eq!(suggestion("Ruy Lopes").as_deref(), Some("Ruy Lopez"));
+        assert_eq!(suggestion("Nonexistent Opening"), None);
+        assert_eq!(suggestion("Sicilian Defense"), None);
+        assert_eq!(
+            get_opening_from_name("Sicilan Defense")
+                .unwrap_err()
+                .to_string(),
+            "No opening found, did you mean Sicilian Defense?"
+        );
+    }
+
+    #[test]
+    fn test_uncapturable_en_passant_square() {
+        // no white pawn stands next to d5, yet the FEN has an en passant square
+        let fen = "rnbqkbnr/ppp2ppp/4p3/3p4/3PP3/8/PPP2PPP/RNBQKBNR w KQkq d6 0 3";
+        assert_eq!(get_opening_from_fen(fen, None).unwrap(), "French Defense");
+        assert_eq!(
+            get_full_opening_from_fen(fen).unwrap().name,
+            "French Defense"
+        );
+        assert!(is_in_opening_book(fen.to_string()).unwrap());
+
+        // the square is kept when the capture is possible
+        let fen: Fen = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3"
+            .parse()
+            .unwrap();
+        assert!(normalize_setup(fen.into_setup()).ep_square.is_some());
+    }
+
+    #[test]
+    fn test_classify_positions() {
+        let fens = [
+            "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "not a fen",
+        ];
+        let openings = classify_positions(fens.map(str::to_string).to_vec()).unwrap();
+        let names: Vec<Option<&str>> = openings
+            .iter()
+            .map(|o| o.as_ref().map(|o| o.name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [Some("Italian Game"), Some("Starting Position"), None, None]
+        );
+        assert!(classify_positions(Vec::new()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_in_opening_book() {
+        let in_book = |fen: &str| is_in_opening_book(fen.to_string());
+        assert!(
+            in_book("r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3").unwrap()
+        );
+        assert!(!in_book("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap());
+        assert!(!in_book("8/8/8/8/8/8/8/8 w - - 0 1").unwrap());
+        assert!(!in_book("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap());
+        assert!(matches!(
+            in_book("not a fen"),
+            Err(Error::InvalidFen { .. })
+        ));
+    }
+
+    #[test]
+    fn test_list_opening_names() {
+        let names = |starts_with, limit, offset| -> Vec<String> {
+            list_opening_names(starts_with, limit, offset)
+                .unwrap()
+                .into_iter()
+                .map(|o| o.name)
+                .collect()
+        };
+        let first_page = names(Some('S'), 10, 0);
+        let second_page = names(Some('s'), 10, 10);
+        assert_eq!(first_page.len(), 10);
+        assert_eq!(second_page.len(), 10);
+        assert_eq!([first_page, second_page].concat(), names(Some('S'), 20, 0));
+
+        let all = names(Some('S'), usize::MAX, 0);
+        assert!(all.iter().all(|n| n.starts_with('S')));
+        assert!(all
+            .windows(2)
+            .all(|w| normalize_name(&w[0]) <= normalize_name(&w[1])));
+        assert!(names(Some('S'), 10, all.len()).is_empty());
+        assert!(names(Some('R'), usize::MAX, 0).contains(&"Réti Opening".to_string()));
+
+        assert_eq!(names(None, usize::MAX, 0).len(), OPENINGS_BY_NAME.len());
+        assert_eq!(names(None, 5, 3), names(None, 8, 0)[3..]);
+    }
+
+    #[test]
+    fn test_split_opening_name() {
+        let split = |name: &str| split_opening_name(name.to_string()).unwrap();
+        assert_eq!(
+            split("Italian Game"),
+            NameParts {
+                family: "Italian Game".to_string(),
+                variation: None,
+                sub_variations: vec![],
+            }
+        );
+        assert_eq!(
+            split("Sicilian Defense: Najdorf Variation, English Attack"),
+            NameParts {
+                family: "Sicilian Defense".to_string(),
+                variation: Some("Najdorf Variation".to_string()),
+                sub_variations: vec!["English Attack".to_string()],
+            }
+        );
+        assert_eq!(
+            split("Sicilian Defense: Najdorf Variation, English Attack, Anti-English")
+                .sub_variations,
+            ["English Attack", "Anti-English"]
+        );
+        assert!(matches!(
+            split_opening_name(" ".to_string()),
+            Err(Error::NoOpeningFound)
+        ));
+    }
+
+    #[test]
+    fn test_user_openings() {
+        let tsv = "eco\tname\tpgn\n\
+                   C20\tMy Bongcloud\t1. e4 e5 2. Ke2\n\
+                   A00\tMy Flank Line\t1. h4 h5 2. a4\n";
+        let mut user = UserOpenings::default();
+        assert_eq!(user.load(tsv.as_bytes()).unwrap(), 2);
+
+        let bongcloud = OPENINGS
+            .iter()
+            .copied()
+            .find(|o| o.name == "Bongcloud Attack")
+            .unwrap();
+        let opening = user
+            .find_by_setup(&normalize_setup(bongcloud.setup.clone()))
+            .unwrap();
+        assert_eq!(opening.name, "My Bongcloud");
+
+        let italian = OPENINGS.iter().find(|o| o.name == "Italian Game").unwrap();
+        let opening = user
+            .find_by_setup(&normalize_setup(italian.setup.clone()))
+            .unwrap();
+        assert_eq!(opening.name, "Italian Game");
+
+        // the positions along the user lines are in book too
+        let flank = normalize_setup(setup_from_pgn("1. h4 h5").unwrap());
+        assert!(user.is_book_position(&flank));
+        assert!(!UserOpenings::default().is_book_position(&flank));
+        assert!(user.is_book_position(&normalize_setup(italian.setup.clone())));
+
+        // the names it returns can be looked up again, like the bundled ones
+        assert_eq!(
+            user.find_by_name("My  Bongcloud").unwrap().pgn(),
+            bongcloud.pgn()
+        );
+        assert_eq!(user.find_by_name("Italian Game").unwrap().eco, "C50");
+        assert!(user.find_by_name("Nonexistent Opening").is_none());
+        let names: Vec<&str> = user
+            .find_all_by_setup(&normalize_setup(bongcloud.setup.clone()))
+            .iter()
+            .map(|o| o.name.as_str())
+            .collect();
+        assert_eq!(names, ["My Bongcloud", "Bongcloud Attack"]);
+
+        let board = |user: &UserOpenings, pgn| {
+            let setup = setup_from_pgn(pgn).unwrap();
+            user.find_by_board(&setup.board, setup.turn)
+                .map(|o| o.name.clone())
+        };
+        assert_eq!(
+            board(&user, "1. e4 e5 2. Ke2").as_deref(),
+            Some("My Bongcloud")
+        );
+        assert_eq!(
+            board(&user, "1. h4 h5 2. a4").as_deref(),
+            Some("My Flank Line")
+        );
+        assert_eq!(board(&UserOpenings::default(), "1. h4 h5 2. a4"), None);
+
+        let matches = user.find_best_matches("my flank line", 1, DEFAULT_MIN_SCORE);
+        assert_eq!(matches[0].name, "My Flank Line");
+    }
+
+    #[test]
+    fn test_user_openings_illegal_move() {
+        let tsv = "eco\tname\tpgn\n\
+                   C20\tMy Bongcloud\t1. e4 e5 2. Ke2\n\
+                   C20\tMy Typo\t1. e4 e5 2. Ke3\n";
+        let mut user = UserOpenings::default();
+        assert!(matches!(
+            user.load(tsv.as_bytes()),
+            Err(Error::IllegalMoveAtPly(3, token)) if token == "Ke3"
+        ));
+        assert!(user.openings.is_empty());
+
+        assert!(matches!(
+            replay_pgn("1. e4 e5 2. Nf3 Nc6 3. Bb4", |_| {}),
+            Err(Error::IllegalMoveAtPly(5, _))
+        ));
+        assert!(replay_pgn("1. e4 e5 2. Nf3 Nc6 3. Bb5", |_| {}).is_ok());
+    }
+
+    #[test]
+    fn test_long_algebraic_moves() {
+        let italian = find_by_name("Italian Game").unwrap();
+        for pgn in [
+            "1. e4 e5 2. g1f3 Nc6 3. Bc4",
+            "1. e2-e4 e7-e5 2. Ng1-f3 Nb8-c6 3. Bf1-c4",
+            "1. e4 e5 2. Ngf3 b8c6 3. Bf1c4",
+        ] {
+            let setup = setup_from_pgn(pgn).unwrap();
+            assert_eq!(setup, italian.setup, "{}", pgn);
+
+            // the helpers working on the moves of a line read them the same way
+            let opening = Opening::new(
+                "C50".to_string(),
+                "Mine".to_string(),
+                setup,
+                Some(pgn.to_string()),
+                0,
+            );
+            assert_eq!(opening_line(&opening), opening_line(&italian), "{}", pgn);
+        }
+
+        let pos = replay_pgn("1. e4 d5 2. e4xd5 Qd8xd5", |_| {}).unwrap();
+        assert_eq!(
+            Fen::from_position(pos, EnPassantMode::Legal).to_string(),
+            "rnb1kbnr/ppp1pppp/8/3q4/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3"
+        );
+        assert_eq!(long_algebraic("e7xd8=Q+").unwrap().to_string(), "e7d8q");
+        assert!(long_algebraic("O-O").is_none());
+        assert!(matches!(
+            replay_pgn("1. e4 e5 2. g1g3", |_| {}),
+            Err(Error::IllegalMoveAtPly(3, _))
+        ));
+    }
+
+    #[test]
+    fn test_recent_openings() {
+        let mut recent = RecentOpenings::default();
+        for name in [
+            "Italian Game",
+            "Sicilian Defense",
+            "Ruy Lopez",
+            "Italian Game",
+        ] {
+            recent.record(name);
+        }
+        let names = |recent: &RecentOpenings, limit| -> Vec<String> {
+            recent.openings(limit).into_iter().map(|o| o.name).collect()
+        };
+        assert_eq!(
+            names(&recent, 10),
+            ["Italian Game", "Ruy Lopez", "Sicilian Defense"]
+        );
+        assert_eq!(names(&recent, 2), ["Italian Game", "Ruy Lopez"]);
+
+        // a name that stopped resolving is skipped instead of failing the list
+        recent.record("Opening Unloaded Since");
+        assert_eq!(names(&recent, 2), ["Italian Game", "Ruy Lopez"]);
+
+        let others = OPENINGS_BY_NAME
+            .keys()
+            .filter(|n| !n.starts_with("Sicilian"));
+        for name in others.take(RECENT_OPENINGS_CAPACITY) {
+            recent.record(name);
+        }
+        assert_eq!(recent.names.len(), RECENT_OPENINGS_CAPACITY);
+        assert!(!recent.names.iter().any(|n| n == "Sicilian Defense"));
+
+        assert!(record_opening_view("Not An Opening".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_ndjson_user_openings() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            r#"{"eco":"C20","name":"My Bongcloud","pgn":"1. e4 e5 2. Ke2"}
+
+{"eco":"A00","name":"My Flank Line","uci":"h2h4 h7h5 a2a4","epd":"ignored"}
+"#,
+        )
+        .unwrap();
+        let mut user = UserOpenings::default();
+        assert_eq!(
+            user.load_path(file.path().to_path_buf(), UserFormat::Ndjson)
+                .unwrap(),
+            2
+        );
+        assert_eq!(user.openings[1].pgn(), Some("1. h4 h5 2. a4"));
+
+        let bongcloud = OPENINGS
+            .iter()
+            .copied()
+            .find(|o| o.name == "Bongcloud Attack")
+            .unwrap();
+        let opening = user
+            .find_by_setup(&normalize_setup(bongcloud.setup.clone()))
+            .unwrap();
+        assert_eq!(opening.name, "My Bongcloud");
+
+        let user = UserOpenings::from_paths(user.paths.clone()).unwrap();
+        assert_eq!(user.openings.len(), 2);
+
+        let mut user = UserOpenings::default();
+        assert!(user
+            .load_ndjson(r#"{"eco":"A00","name":"Bad","uci":"e2e5"}"#.as_bytes())
+            .is_err());
+        assert!(user
+            .load_ndjson(r#"{"eco":"A00","name":"No Moves"}"#.as_bytes())
+            .is_err());
+        assert!(user.load_ndjson("not json".as_bytes()).is_err());
+        assert!(user.openings.is_empty());
+    }
+
+    #[test]
+    fn test_reload_user_openings() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            "eco\tname\tpgn\nC20\tMy Bongcloud\t1. e4 e5 2. Ke2\n",
+        )
+        .unwrap();
+        let user =
+            UserOpenings::from_paths(vec![(file.path().to_path_buf(), UserFormat::Tsv)]).unwrap();
+        assert_eq!(user.openings.len(), 1);
+
+        std::fs::write(
+            file.path(),
+            "eco\tname\tpgn\nC20\tBongcloud Repertoire\t1. e4 e5 2. Ke2\nA00\tMy Flank Line\t1. h4 h5 2. a4\n",
+        )
+        .unwrap();
+        let user = UserOpenings::from_paths(user.paths.clone()).unwrap();
+        assert_eq!(user.openings.len(), 2);
+
+        let bongcloud = OPENINGS
+            .iter()
+            .copied()
+            .find(|o| o.name == "Bongcloud Attack")
+            .unwrap();
+        let opening = user
+            .find_by_setup(&normalize_setup(bongcloud.setup.clone()))
+            .unwrap();
+        assert_eq!(opening.name, "Bongcloud Repertoire");
+    }
+
+    #[test]
+    fn test_annotated_pgn() {
+        let italian: Fen = "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3"
+            .parse()
+            .unwrap();
+        let setup = setup_from_pgn("1. e4!? e5 2. Nf3 $1 Nc6?! 3. Bc4 {Italian}").unwrap();
+        assert_eq!(setup, italian.clone().into_setup());
+
+        // the moves named in comments and variations aren't played
+        let setup =
+            setup_from_pgn("1. e4 {Black answers e5} e5 2. Nf3 (2. f4 exf4) 2... Nc6 3.Bc4 1-0")
+                .unwrap();
+        assert_eq!(setup, italian.into_setup());
+    }
+
+    #[test]
+    fn test_compressed_tsv_matches_raw() {
+        const RAW_TSV_DATA: [&[u8]; 5] = [
+            include_bytes!("../data/a.tsv"),
+            include_bytes!("../data/b.tsv"),
+            include_bytes!("../data/c.tsv"),
+            include_bytes!("../data/d.tsv"),
+            include_bytes!("../data/e.tsv"),
+        ];
+        for (raw, compressed) in RAW_TSV_DATA.iter().zip(TSV_DATA) {
+            assert_eq!(
+                read_tsv_volume(compressed).len(),
+                read_openings(*raw).0.len()
+            );
+        }
+
+        assert!(decompress(b"not gzip").is_none());
+    }
+
+    #[test]
+    fn test_openings_index_matches_tsv() {
+        let mut total = 0;
+        for (index, tsv) in INDEX_DATA.iter().zip(TSV_DATA) {
+            let index = read_openings_index(index).unwrap();
+            let tsv = read_tsv_volume(tsv);
+            assert_eq!(index.len(), tsv.len());
+            total += index.len();
+
+            for (indexed, parsed) in index.iter().zip(&tsv) {
+                assert_eq!(indexed.eco, parsed.eco);
+                assert_eq!(indexed.name, parsed.name);
+                assert_eq!(indexed.setup, parsed.setup);
+                assert_eq!(indexed.pgn, parsed.pgn);
+            }
+        }
+        assert_eq!(OPENINGS.len(), total + 2);
+
+        let index = read_openings_index(INDEX_DATA[2]).unwrap();
+        let italian = index.iter().find(|o| o.name == "Italian Game").unwrap();
+        assert_eq!(italian.eco, "C50");
+        assert_eq!(
+            Fen::from_setup(italian.setup.clone()).to_string(),
+            "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3"
+        );
+
+        assert!(read_openings_index(b"not gzip").is_err());
+    }
+
+    #[test]
+    fn test_dataset_version() {
+        let version = opening_dataset_version();
+        assert_eq!(version.len(), 16);
+        assert!(version.bytes().all(|b| b.is_ascii_hexdigit()));
+        assert_eq!(opening_dataset_version(), version);
+
+        let fixture: [&[u8]; 2] = [b"eco\tname\tpgn\nA00\tAmar Opening\t1. Nh3\n", b""];
+        let changed: [&[u8]; 2] = [b"eco\tname\tpgn\nA00\tAmar Opening\t1. Nh3 d5\n", b""];
+        assert_eq!(dataset_version(fixture), dataset_version(fixture));
+        assert_ne!(dataset_version(fixture), dataset_version(changed));
+        assert_ne!(dataset_version(fixture), version);
+    }
+
+    #[test]
+    fn test_volumes_load_lazily() {
+        let volumes = Volumes::new();
+        let setup = normalize_setup(setup_from_pgn("1. Nf3").unwrap());
+        let opening = volumes.find_by_setup(&setup).unwrap();
+        assert!(opening.eco.starts_with('A'));
+        assert_eq!(volumes.loads.load(atomic::Ordering::Relaxed), 1);
+        assert!(volumes.volumes[4].get().is_none());
+
+        assert_eq!(volumes.iter().count(), OPENINGS.len() - 2);
+        assert_eq!(volumes.loads.load(atomic::Ordering::Relaxed), 5);
+    }
+
+    #[test]
+    fn test_volumes_from_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        for (volume, line) in ["a", "b", "c", "d", "e"].iter().zip([
+            "A00\tFixture Opening\t1. a3",
+            "B00\tFixture King's Pawn\t1. e4",
+            "C00\tFixture French\t1. e4 e6",
+            "D00\tFixture Queen's Pawn\t1. d4",
+            "E00\tFixture Indian\t1. d4 Nf6 2. c4 e6",
+        ]) {
+            let tsv = format!("eco\tname\tpgn\n{}\n", line);
+            std::fs::write(dir.path().join(format!("{}.tsv", volume)), tsv).unwrap();
+        }
+
+        let volumes = Volumes::new();
+        assert_eq!(volumes.load_dir(dir.path().to_path_buf()).unwrap(), 5);
+        let setup = normalize_setup(setup_from_pgn("1. e4").unwrap());
+        assert_eq!(
+            volumes.find_by_setup(&setup).unwrap().name,
+            "Fixture King's Pawn"
+        );
+        assert_eq!(volumes.iter().count(), 5);
+        assert!(matches!(
+            volumes.load_dir(dir.path().to_path_buf()),
+            Err(Error::OpeningsAlreadyLoaded)
+        ));
+
+        // a missing volume falls back to the bundled data
+        let volumes = Volumes::new();
+        volumes.dir.set(Some(dir.path().join("missing"))).unwrap();
+        assert_eq!(volumes.get(0).openings[0].name, "Amar Gambit");
+        assert!(volumes.load_dir(dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_mirrored_opening() {
+        let sicilian = OPENINGS
+            .iter()
+            .copied()
+            .find(|o| o.pgn.as_deref() == Some("1. e4 c5 2. Nf3"))
+            .unwrap();
+        let mirrored = mirror_setup(sicilian.setup.clone());
+        assert_eq!(mirror_setup(mirrored.clone()), sicilian.setup);
+
+        let fen = Fen::from_setup(mirrored).to_string();
+        let result = get_opening_from_mirrored_fen(&fen).unwrap();
+        assert!(result.mirrored);
+        assert_eq!(result.opening.name, sicilian.name);
+
+        let fen = Fen::from_setup(sicilian.setup.clone()).to_string();
+        let result = get_opening_from_mirrored_fen(&fen).unwrap();
+        assert!(!result.mirrored);
+        assert_eq!(result.opening.name, sicilian.name);
+    }
+
+    #[test]
+    fn test_variant_openings() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(
+            get_opening_from_fen(start, None).unwrap(),
+            "Starting Position"
+        );
+        assert!(get_opening_from_fen(start, Some(GameVariant::Chess960)).is_err());
+        assert!(get_opening_from_fen(start, Some(GameVariant::Crazyhouse)).is_err());
+
+        let chess960 = "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1";
+        assert!(get_opening_from_fen(chess960, Some(GameVariant::Chess960)).is_err());
+    }
+
+    #[test]
+    fn test_validate_fen() {
+        assert!(validate_fen(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string()
+        )
+        .is_ok());
+        assert!(matches!(
+            validate_fen("rnbqkbnr/pppppppp/8/8 w KQkq".to_string()),
+            Err(Error::InvalidFen { .. })
+        ));
+        assert!(matches!(
+            validate_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBKR w kq - 0 1".to_string()),
+            Err(Error::InvalidKingCount)
+        ));
+        assert!(matches!(
+            validate_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w KQkq - 0 1".to_string()),
+            Err(Error::InvalidCastlingRights)
+        ));
+        assert!(matches!(
+            validate_fen("rnbqkbnr/pppp1ppp/8/8/8/8/PPPPqPPP/RNBQKBNR b KQkq - 0 1".to_string()),
+            Err(Error::OppositeCheck)
+        ));
+
+        assert!(matches!(
+            position_error(PositionErrorKinds::IMPOSSIBLE_CHECK),
+            Error::ImpossibleCheck
+        ));
+        assert!(matches!(
+            position_error(PositionErrorKinds::VARIANT),
+            Error::InvalidPosition(kinds) if kinds == PositionErrorKinds::VARIANT
+        ));
+    }
+
+    fn moves(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|m| m.to_string()).collect()
+    }
+
+    #[test]
+    fn test_deepest_opening() {
+        let opening = get_deepest_opening(moves("e4 e5 Nf3 Nc6 Bb5 a6 Kf1 Ke7 Ke1 Ke8")).unwrap();
+        assert_eq!(opening.name, "Ruy Lopez: Morphy Defense");
+
+        let opening = get_deepest_opening(vec![]).unwrap();
+        assert_eq!(opening.name, "Starting Position");
+
+        assert!(matches!(
+            get_deepest_opening(moves("e4 e5 e4")),
+            Err(Error::IllegalMoveAtPly(3, _))
+        ));
+    }
+
+    #[test]
+    fn test_opening_from_pgn() {
+        let italian = |movetext: &str| get_opening_from_pgn(movetext.to_string()).unwrap().name;
+        assert_eq!(italian("1. e4 e5 2. Nf3 Nc6 3. Bc4"), "Italian Game");
+        assert_eq!(italian("1.e4 e5 2.Nf3 Nc6 3.Bc4 1-0"), "Italian Game");
+        assert_eq!(
+            italian("1. e4 e5 2. Nf3 Nc6 3. Bc4 1/2-1/2"),
+            "Italian Game"
+        );
+        assert_eq!(
+            italian("1. e4 {main line} e5 2. Nf3 (2. f4 exf4) 2... Nc6 $1 3. Bc4!? *"),
+            "Italian Game"
+        );
+
+        assert_eq!(movetext_moves("1. e4 e5 2. Nf3 0-1"), moves("e4 e5 Nf3"));
+        assert!(matches!(
+            get_opening_from_pgn("1. e4 e5 2. Ke3".to_string()),
+            Err(Error::IllegalMoveAtPly(3, _))
+        ));
+    }
+
+    #[test]
+    fn test_is_book_move() {
+        let ruy_lopez = get_fen_from_opening_name("Ruy Lopez").unwrap();
+        let book_move = |san: &str| is_book_move(ruy_lopez.clone(), san.to_string());
+        assert!(book_move("a6").unwrap());
+        assert!(book_move("Nf6").unwrap());
+        assert!(!book_move("Rb8").unwrap());
+        assert!(book_move("Bb3").is_err());
+        assert!(book_move("xyz").is_err());
+    }
+
+    #[test]
+    fn test_opening_from_uci() {
+        let opening = get_opening_from_uci(moves("e2e4 e7e5 g1f3 b8c6 f1c4")).unwrap();
+        assert_eq!(opening.name, "Italian Game");
+        let opening = get_opening_from_uci(moves("e2e4 e7e5 g1f3 b8c6 f1c4 g8f6 e1g1")).unwrap();
+        assert_eq!(
+            opening.name,
+            get_deepest_opening(moves("e4 e5 Nf3 Nc6 Bc4 Nf6 O-O"))
+                .unwrap()
+                .name
+        );
+        assert!(matches!(
+            get_opening_from_uci(moves("e2e4 e7e6 e1e3")),
+            Err(Error::IllegalMoveAtPly(3, _))
+        ));
+        assert!(matches!(
+            get_opening_from_uci(moves("e2e4 Nf6")),
+            Err(Error::IllegalMoveAtPly(2, _))
+        ));
+
+        let fen: Fen = "8/P7/8/8/8/8/8/k6K w - - 0 1".parse().unwrap();
+        let pos = Chess::from_setup(fen.into_setup(), CastlingMode::Standard).unwrap();
+        let m = parse_uci_move(&pos, 1, "a7a8q").unwrap();
+        assert_eq!(m.promotion(), Some(Role::Queen));
+        assert!(parse_uci_move(&pos, 1, "a7a8").is_err());
+    }
+
+    #[test]
+    fn test_opening_from_truncated_moves() {
+        let line = moves("e4 e5 Nf3 Nc6 Bc4 a5");
+        let name = |ply| {
+            get_opening_from_moves_truncated(line.clone(), ply)
+                .unwrap()
+                .map(|o| o.name)
+        };
+        assert_eq!(name(0).as_deref(), Some("Starting Position"));
+        assert_eq!(name(1).as_deref(), Some("King's Pawn"));
+        assert_eq!(name(3).as_deref(), Some("King's Knight Opening"));
+        assert_eq!(name(5).as_deref(), Some("Italian Game"));
+        assert_eq!(name(6), None);
+        assert!(matches!(
+            get_opening_from_moves_truncated(line.clone(), 7),
+            Err(Error::PlyOutOfRange(7, 6))
+        ));
+
+        // moves past the truncation aren't replayed
+        let opening = get_opening_from_moves_truncated(moves("e4 e5 Ke3"), 2).unwrap();
+        assert_eq!(opening.unwrap().name, "King's Pawn Game");
+    }
+
+    #[test]
+    fn test_classify_game() {
+        let openings = classify_game(moves("e4 e5 Nf3 Nc6 Bc4 a5 a4")).unwrap();
+        let names: Vec<Option<&str>> = openings
+            .iter()
+            .map(|o| o.as_ref().map(|o| o.name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                Some("King's Pawn"),
+                Some("King's Pawn Game"),
+                Some("King's Knight Opening"),
+                Some("King's Knight Opening: Normal Variation"),
+                Some("Italian Game"),
+                None,
+                None,
+            ]
+        );
+
+        assert!(matches!(
+            classify_game(moves("e4 e5 Ke3")),
+            Err(Error::IllegalMoveAtPly(3, _))
+        ));
+    }
+
+    #[test]
+    fn test_find_novelty() {
+        let report = find_novelty(moves("e4 e5 Nf3 Nc6 Bb5 a6")).unwrap();
+        assert_eq!(report.last_book_ply, 6);
+        assert_eq!(report.novelty_ply, None);
+        assert_eq!(report.opening.name, "Ruy Lopez: Morphy Defense");
+
+        let report = find_novelty(moves("e4 b5 Nf3")).unwrap();
+        assert_eq!(report.last_book_ply, 1);
+        assert_eq!(report.novelty_ply, Some(2));
+        assert_eq!(report.opening.name, "King's Pawn");
+    }
+
+    #[test]
+    fn test_repeated_lookups() {
+        let setups: Vec<Setup> = OPENINGS.iter().map(|o| o.setup.clone()).collect();
+        for i in 0..10_000 {
+            let setup = setups[i % setups.len()].clone();
+            assert!(get_opening_from_setup(setup, GameVariant::Standard).is_ok());
+        }
     }
 }
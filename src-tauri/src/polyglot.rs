@@ -0,0 +1,175 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::PathBuf,
+};
+
+use serde::Serialize;
+use shakmaty::{
+    fen::Fen,
+    san::San,
+    zobrist::{Zobrist64, ZobristHash},
+    CastlingMode, Chess, EnPassantMode, Move, Position, Role, Square,
+};
+
+use crate::error::Error;
+
+/// Size of an entry of a polyglot book: key, move, weight and learn fields
+const ENTRY_SIZE: u64 = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    key: u64,
+    mv: u16,
+    weight: u16,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BookMove {
+    san: String,
+    uci: String,
+    weight: u16,
+}
+
+/// Returns the legal moves a polyglot book has for the position, by descending weight
+#[tauri::command]
+pub fn get_book_moves(fen: String, book_path: PathBuf) -> Result<Vec<BookMove>, Error> {
+    let fen: Fen = fen.parse()?;
+    let pos = Chess::from_setup(fen.into_setup(), CastlingMode::Standard)?;
+    let book = BufReader::new(File::open(book_path)?);
+    book_moves(book, &pos)
+}
+
+fn book_moves<R: Read + Seek>(mut book: R, pos: &Chess) -> Result<Vec<BookMove>, Error> {
+    let key = polyglot_key(pos);
+    let mut moves: Vec<BookMove> = find_entries(&mut book, key)?
+        .into_iter()
+        .filter_map(|entry| {
+            let m = decode_move(pos, entry.mv)?;
+            Some(BookMove {
+                san: San::from_move(pos, &m).to_string(),
+                uci: m.to_uci(CastlingMode::Standard).to_string(),
+                weight: entry.weight,
+            })
+        })
+        .collect();
+    moves.sort_by(|a, b| b.weight.cmp(&a.weight));
+    Ok(moves)
+}
+
+/// Polyglot books use the same 64-bit Zobrist keys as shakmaty, with an en passant
+/// square only counted when a pawn stands next to the pushed one
+pub(crate) fn polyglot_key(pos: &Chess) -> u64 {
+    let hash: Zobrist64 = pos.zobrist_hash(EnPassantMode::PseudoLegal);
+    hash.0
+}
+
+fn read_entry<R: Read>(book: &mut R) -> Result<Entry, Error> {
+    let mut buf = [0; ENTRY_SIZE as usize];
+    book.read_exact(&mut buf)?;
+    Ok(Entry {
+        key: u64::from_be_bytes(buf[0..8].try_into().unwrap()),
+        mv: u16::from_be_bytes(buf[8..10].try_into().unwrap()),
+        weight: u16::from_be_bytes(buf[10..12].try_into().unwrap()),
+    })
+}
+
+/// Binary searches the entries of a book, which are sorted by key
+fn find_entries<R: Read + Seek>(book: &mut R, key: u64) -> Result<Vec<Entry>, Error> {
+    let len = book.seek(SeekFrom::End(0))? / ENTRY_SIZE;
+
+    let (mut lo, mut hi) = (0, len);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        book.seek(SeekFrom::Start(mid * ENTRY_SIZE))?;
+        if read_entry(book)?.key < key {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+
+    let mut entries = Vec::new();
+    book.seek(SeekFrom::Start(lo * ENTRY_SIZE))?;
+    for _ in lo..len {
+        let entry = read_entry(book)?;
+        if entry.key != key {
+            break;
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Finds the legal move matching a polyglot move, where castling is encoded
+/// as the king moving to its rook
+fn decode_move(pos: &Chess, mv: u16) -> Option<Move> {
+    let square = |bits: u16| Square::new(u32::from(bits & 0o77));
+    let from = square(mv >> 6);
+    let to = square(mv);
+    let promotion = match (mv >> 12) & 0b111 {
+        0 => None,
+        1 => Some(Role::Knight),
+        2 => Some(Role::Bishop),
+        3 => Some(Role::Rook),
+        4 => Some(Role::Queen),
+        _ => return None,
+    };
+
+    pos.legal_moves()
+        .into_iter()
+        .find(|m| m.from() == Some(from) && m.to() == to && m.promotion() == promotion)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn entry(key: u64, mv: u16, weight: u16) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(key.to_be_bytes());
+        bytes.extend(mv.to_be_bytes());
+        bytes.extend(weight.to_be_bytes());
+        bytes.extend(0u32.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_polyglot_key() {
+        let pos = Chess::default();
+        assert_eq!(polyglot_key(&pos), 0x463b96181691fc9c);
+
+        let fen: Fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+            .parse()
+            .unwrap();
+        let pos = Chess::from_setup(fen.into_setup(), CastlingMode::Standard).unwrap();
+        assert_eq!(polyglot_key(&pos), 0x823c9b50fd114196);
+    }
+
+    #[test]
+    fn test_book_moves() {
+        let start = 0x463b96181691fc9c;
+        let mut book = Vec::new();
+        book.extend(entry(0x1000, 796, 1));
+        // e2e4 and d2d4
+        book.extend(entry(start, 796, 10));
+        book.extend(entry(start, 731, 20));
+        // e1h1 castling, illegal in the starting position
+        book.extend(entry(start, 263, 30));
+        book.extend(entry(u64::MAX, 796, 1));
+
+        let moves = book_moves(Cursor::new(book.clone()), &Chess::default()).unwrap();
+        let uci: Vec<&str> = moves.iter().map(|m| m.uci.as_str()).collect();
+        assert_eq!(uci, ["d2d4", "e2e4"]);
+        assert_eq!(moves[0].san, "d4");
+        assert_eq!(moves[0].weight, 20);
+
+        let fen: Fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+            .parse()
+            .unwrap();
+        let pos = Chess::from_setup(fen.into_setup(), CastlingMode::Standard).unwrap();
+        assert!(book_moves(Cursor::new(book), &pos).unwrap().is_empty());
+    }
+}
@@ -69,9 +69,9 @@ return await TAURI_INVOKE("plugin:tauri-specta|set_menu_visisble", { state });
 async isMenuVisisble() : Promise<boolean> {
 return await TAURI_INVOKE("plugin:tauri-specta|is_menu_visisble");
 },
-async getOpeningFromFen(fen: string) : Promise<__Result__<string, string>> {
+async getOpeningFromFen(fen: string, variant: GameVariant | null) : Promise<__Result__<string, string>> {
 try {
-    return { status: "ok", data: await TAURI_INVOKE("plugin:tauri-specta|get_opening_from_fen", { fen }) };
+    return { status: "ok", data: await TAURI_INVOKE("plugin:tauri-specta|get_opening_from_fen", { fen, variant }) };
 } catch (e) {
     if(e instanceof Error) throw e;
     else return { status: "error", error: e  as any };
@@ -85,6 +85,14 @@ try {
     else return { status: "error", error: e  as any };
 }
 },
+async getFenFromOpeningName(name: string) : Promise<__Result__<string, string>> {
+try {
+    return { status: "ok", data: await TAURI_INVOKE("plugin:tauri-specta|get_fen_from_opening_name", { name }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 async getPlayersGameInfo(file: string, id: number) : Promise<__Result__<{ won: number; lost: number; draw: number; data_per_month: ([string, MonthData])[]; white_openings: ([string, Results])[]; black_openings: ([string, Results])[] }, string>> {
 try {
     return { status: "ok", data: await TAURI_INVOKE("plugin:tauri-specta|get_players_game_info", { file, id }) };
@@ -110,6 +118,7 @@ export type BestMoves = { nodes: number; depth: number; score: Score; uciMoves:
 export type BestMovesPayload = { bestLines: BestMoves[]; engine: string; tab: string; fen: string; moves: string[]; progress: number }
 export type EngineOption = { name: string; value: string }
 export type EngineOptions = { multipv: number; threads: number; hash: number; fen: string; moves: string[]; extraOptions: EngineOption[] }
+export type GameVariant = "standard" | "chess960" | "antichess" | "atomic" | "crazyhouse" | "horde" | "kingOfTheHill" | "racingKings" | "threeCheck"
 export type GoMode = { t: "Depth"; c: number } | { t: "Time"; c: number } | { t: "Nodes"; c: number } | { t: "Infinite" }
 export type MonthData = { count: number; avg_elo: number }
 export type Progress = { id: string; progress: number }
@@ -21,6 +21,8 @@ export default function FenSearch({ currentFen }: { currentFen: string }) {
 
   const [error, setError] = useState<FenError | undefined>(undefined);
   const dispatch = useContext(TreeDispatchContext);
+  // only this input's newer searches cancel its older ones
+  const searchCaller = useRef(crypto.randomUUID());
 
   function addFen(fen: string) {
     if (fen) {
@@ -45,14 +47,23 @@ export default function FenSearch({ currentFen }: { currentFen: string }) {
 
   const { data, isLoading } = useSWRImmutable(
     ["search_opening_name", search],
-    ([, search]) =>
-      invoke<
+    async ([, search]) => {
+      const generation = await invoke<number>("next_search_generation");
+      return invoke<
         {
           eco: string;
           name: string;
           fen: string;
         }[]
-      >("search_opening_name", { query: search }),
+      >(
+        "search_opening_name",
+        {
+          query: search,
+          generation: { caller: searchCaller.current, generation },
+        },
+        (e) => e.startsWith("Search cancelled"),
+      );
+    },
   );
 
   const exactOptionMatch = data?.some((item) => item.fen === search);
@@ -320,7 +320,7 @@ export async function getOpening(
   if (tree === null) {
     return "";
   }
-  const res = await commands.getOpeningFromFen(tree.fen);
+  const res = await commands.getOpeningFromFen(tree.fen, null);
   if (res.status === "error") {
     if (position.length === 0) {
       return "";